@@ -1,57 +1,51 @@
-use api_interface::{Apis, DatabaseCredentials};
+use api_core::{api::MutateListings, memory::InMemoryStore, reexports::uuid::Uuid, Listing};
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use fake::{faker::lorem::en::Words, Fake};
+use time::OffsetDateTime;
 
 fn bench(c: &mut Criterion) {
-    dotenvy::dotenv().ok();
-    let rt = tokio::runtime::Runtime::new().unwrap();
-
-    let db_host = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL");
-    let db_host = db_host.replace("http://", "");
-
-    let username = std::env::var("TEST_DATABASE_USERNAME").expect("TEST_DATABASE_USERNAME");
-    let password = std::env::var("TEST_DATABASE_PASSWORD").expect("TEST_DATABASE_PASSWORD");
-    let db_name = std::env::var("TEST_DATABASE_NAME").expect("TEST_DATABASE_NAME");
-
-    let api_users = std::env::var("TEST_SELLERSHUT_API_USERS").expect("TEST_SELLERSHUT_API_USERS");
-    let api_categories =
-        std::env::var("TEST_SELLERSHUT_API_CATEGORIES").expect("TEST_SELLERSHUT_API_CATEGORIES");
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
 
-    let credentials = DatabaseCredentials {
-        db_dsn: &db_host,
-        db_user: &username,
-        db_pass: &password,
-        db_ns: "benchmarks",
-        db: &db_name,
-    };
+    let size = 100;
+    let database = InMemoryStore::new();
+    let (user, category, condition) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
 
-    let apis = Apis {
-        users: &api_users,
-        categories: &api_categories,
-    };
+    for _ in 0..size {
+        let title: Vec<String> = Words(3..5).fake();
+        let listing = Listing {
+            id: Uuid::now_v7(),
+            title: title.join(" "),
+            description: String::default(),
+            price: 250.into(),
+            image_url: String::from("https://dummyimage.com/420x260"),
+            other_images: vec![],
+            status: api_core::ListingStatus::Active,
+            negotiable: true,
+            created: OffsetDateTime::now_utc(),
+            expires: None,
+            updated: OffsetDateTime::now_utc(),
+            deleted: None,
+        };
 
-    let schema = rt
-        .block_on(api_interface::ApiSchemaBuilder::new(
-            credentials,
-            None,
-            None,
-            apis,
-        ))
-        .unwrap();
+        rt.block_on(database.create_listing(&listing, &user, &category, &condition, 1))
+            .unwrap();
+    }
 
-    let schema = schema.build();
+    let schema = api_interface::ApiSchemaBuilder::with_database(database).build();
 
-    let size = 100;
-    let query = |method: &str, count: u16| {
+    let query = |method: &str, args: &str, count: u16| {
         black_box(format!(
             "
                    query {{
-                       {method}(first: {count}) {{
+                       {method}({args}first: {count}) {{
                        edges{{
                          cursor
                          node{{
                            id,
-                           name,
-                           subCategories,
+                           title,
+                           price,
                            imageUrl
                          }}
                        }},
@@ -65,14 +59,14 @@ fn bench(c: &mut Criterion) {
         ))
     };
 
-    c.bench_with_input(BenchmarkId::new("categories", size), &size, |b, &s| {
+    c.bench_with_input(BenchmarkId::new("listings", size), &size, |b, &s| {
         b.to_async(&rt)
-            .iter(|| black_box(schema.execute(query("categories", s))));
+            .iter(|| black_box(schema.execute(query("listings", "", s))));
     });
 
-    c.bench_with_input(BenchmarkId::new("subCategories", size), &size, |b, &s| {
+    c.bench_with_input(BenchmarkId::new("search", size), &size, |b, &s| {
         b.to_async(&rt)
-            .iter(|| black_box(schema.execute(query("subCategories", s))));
+            .iter(|| black_box(schema.execute(query("search", "query: \"a\", ", s))));
     });
 }
 
@@ -1,6 +1,7 @@
-use api_core::Listing;
+use api_core::{Listing, ListingStatus};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use fake::{faker::lorem::en::Words, Fake};
+use rust_decimal::Decimal;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
@@ -12,24 +13,19 @@ fn bench(c: &mut Criterion) {
         let words: Vec<String> = Words(1..5).fake();
         let words = words.join(" ");
 
-        let tags: Vec<_> = [0; 4].iter().map(|_| Uuid::now_v7()).collect();
-
         let listing = Listing {
             id: Uuid::now_v7(),
-            image_url: String::default(),
-            user_id: Uuid::now_v7(),
             title: words,
             description: String::default(),
-            price: 23.5,
-            category_id: Uuid::now_v7(),
+            price: Decimal::new(2350, 2),
+            image_url: String::default(),
             other_images: vec![],
-            active: false,
-            tags,
-            location: String::default(),
-            liked_by: vec![],
-            created_at: OffsetDateTime::now_utc(),
-            updated_at: None,
-            deleted_at: None,
+            status: ListingStatus::Active,
+            negotiable: false,
+            created: OffsetDateTime::now_utc(),
+            expires: None,
+            updated: OffsetDateTime::now_utc(),
+            deleted: None,
         };
 
         listings.push(listing);
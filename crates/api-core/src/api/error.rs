@@ -2,7 +2,9 @@ use std::str::FromStr;
 
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+use crate::ListingStatus;
+
+#[derive(Error, Debug, Clone)]
 pub enum CoreError {
     #[error("`{0}`")]
     Database(String),
@@ -10,6 +12,13 @@ pub enum CoreError {
     Other(String),
     #[error(transparent)]
     Uuid(#[from] uuid::Error),
+    #[error("listing cannot move from {from} to {to}")]
+    InvalidTransition {
+        from: ListingStatus,
+        to: ListingStatus,
+    },
+    #[error("forbidden: {0}")]
+    Forbidden(String),
     #[error("unknown core error")]
     Unknown,
     #[error("unreachable logic")]
@@ -23,3 +32,14 @@ impl FromStr for CoreError {
         Ok(Self::Other(s.to_owned()))
     }
 }
+
+#[cfg(feature = "async-graphql")]
+impl async_graphql::ErrorExtensions for CoreError {
+    fn extend(&self) -> async_graphql::Error {
+        let error = async_graphql::Error::new(self.to_string());
+        match self {
+            Self::Forbidden(_) => error.extend_with(|_, e| e.set("code", "FORBIDDEN")),
+            _ => error,
+        }
+    }
+}
@@ -1,43 +1,95 @@
 mod error;
 pub use std::fmt::Debug;
+use std::{collections::HashMap, time::Duration};
 
-use crate::{Listing, ListingCondition};
+#[cfg(feature = "async-graphql")]
+use async_trait::async_trait;
+use futures_core::Stream;
+use rust_decimal::Decimal;
+
+use crate::{
+    Listing, ListingCondition, ListingEdges, ListingEvent, ListingFilter, ListingSort,
+    ListingStatus, Page, PageRequest, Webhook, WebhookDelivery, WebhookEvent,
+};
 
 pub use error::*;
 pub use uuid::Uuid;
 
 #[trait_variant::make(QueryListings: Send)]
 pub trait LocalQueryListings {
-    async fn get_listings(&self) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError>;
+    async fn get_listings(
+        &self,
+    ) -> Result<impl ExactSizeIterator<Item = Listing> + Send, CoreError>;
     async fn get_listing_by_id(&self, listing_id: &Uuid) -> Result<Option<Listing>, CoreError>;
+    /// Listings that do not exist or were deleted are left out
+    async fn get_listings_by_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<impl ExactSizeIterator<Item = Listing> + Send, CoreError>;
+    async fn get_listing_owner(&self, listing_id: &Uuid) -> Result<Option<Uuid>, CoreError>;
+    /// Listings without edges are left out
+    async fn get_edges_by_listing_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, ListingEdges>, CoreError>;
     async fn get_listings_from_user(
         &self,
         user_id: &Uuid,
-    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError>;
+    ) -> Result<impl ExactSizeIterator<Item = Listing> + Send, CoreError>;
     async fn get_listings_in_category(
         &self,
         category_id: &Uuid,
-    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError>;
+    ) -> Result<impl ExactSizeIterator<Item = Listing> + Send, CoreError>;
     async fn get_listings_with_tags(
         &self,
         tags: &[&Uuid],
-    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError>;
+    ) -> Result<impl ExactSizeIterator<Item = Listing> + Send, CoreError>;
     async fn get_listings_in_price_range(
         &self,
-        min: f64,
-        max: f64,
-    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError>;
+        min: &Decimal,
+        max: &Decimal,
+    ) -> Result<impl ExactSizeIterator<Item = Listing> + Send, CoreError>;
+    /// Listings in a status that match a full text query, most relevant first. Relevance gives
+    /// no key to page by, so up to `limit` listings are returned from position `offset`
     async fn search(
         &self,
         query: impl AsRef<str> + Send + Debug,
-    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError>;
+        status: ListingStatus,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Page<Listing>, CoreError>;
+    async fn find_listings(
+        &self,
+        filter: &ListingFilter,
+        sort: ListingSort,
+        page: PageRequest,
+    ) -> Result<Page<Listing>, CoreError>;
+}
+
+/// Object safe access to the edges of listings, for the GraphQL resolvers on [`Listing`] which
+/// cannot be generic over the storage backend. Implementations are expected to batch the
+/// lookups of a request
+#[cfg(feature = "async-graphql")]
+#[async_trait]
+pub trait ListingEdgeSource: Send + Sync {
+    async fn listing_edges(&self, listing_id: &Uuid)
+        -> async_graphql::Result<Option<ListingEdges>>;
+    async fn listing_condition(
+        &self,
+        listing_id: &Uuid,
+    ) -> async_graphql::Result<Option<ListingCondition>>;
 }
 
 #[trait_variant::make(QueryListingCondition: Send)]
 pub trait LocalQueryListingCondition {
     async fn get_conditions(
         &self,
-    ) -> Result<impl ExactSizeIterator<Item = ListingCondition>, CoreError>;
+    ) -> Result<impl ExactSizeIterator<Item = ListingCondition> + Send, CoreError>;
+    /// Listings without a condition are left out
+    async fn get_conditions_by_listing_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, ListingCondition>, CoreError>;
 }
 
 #[trait_variant::make(MutateListings: Send)]
@@ -61,5 +113,55 @@ pub trait LocalMutateListings {
     ) -> Result<Option<Listing>, CoreError>;
     async fn delete_listing(&self, id: &Uuid, user_id: &Uuid)
         -> Result<Option<Listing>, CoreError>;
+    async fn restore_listing(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Option<Listing>, CoreError>;
+    async fn purge_deleted_listings(&self, retention: Duration) -> Result<usize, CoreError>;
+    /// Delivers up to `batch` of the recorded listing changes that are due, returning how many
+    /// were delivered. Changes that could not be delivered are retried later
+    async fn dispatch_outbox(&self, batch: usize) -> Result<usize, CoreError>;
+    async fn transition_listing(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+        status: ListingStatus,
+    ) -> Result<Option<Listing>, CoreError>;
     async fn upload_images(&self, files: &[&[u8]]) -> Result<Vec<(String, String)>, CoreError>;
 }
+
+#[trait_variant::make(SubscribeListings: Send)]
+pub trait LocalSubscribeListings {
+    async fn live_listings(
+        &self,
+    ) -> Result<impl Stream<Item = ListingEvent> + Send + '_, CoreError>;
+    /// Changes to a single listing
+    async fn watch_listing(
+        &self,
+        listing_id: Uuid,
+    ) -> Result<impl Stream<Item = ListingEvent> + Send + '_, CoreError>;
+}
+
+#[trait_variant::make(ManageWebhooks: Send)]
+pub trait LocalManageWebhooks {
+    async fn create_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+    ) -> Result<Webhook, CoreError>;
+    async fn delete_webhook(&self, id: &Uuid) -> Result<Option<Webhook>, CoreError>;
+    async fn get_webhooks(
+        &self,
+    ) -> Result<impl ExactSizeIterator<Item = Webhook> + Send, CoreError>;
+    /// The latest deliveries to a webhook, newest first
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: &Uuid,
+        limit: usize,
+    ) -> Result<impl ExactSizeIterator<Item = WebhookDelivery> + Send, CoreError>;
+    /// Posts up to `batch` of the deliveries that are due, returning how many succeeded.
+    /// Deliveries that fail are retried later with an exponential backoff
+    async fn dispatch_webhooks(&self, batch: usize) -> Result<usize, CoreError>;
+}
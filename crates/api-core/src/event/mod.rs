@@ -0,0 +1,103 @@
+#[cfg(feature = "async-graphql")]
+use async_graphql::SimpleObject;
+use rust_decimal::Decimal;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Listing, ListingStatus};
+
+/// What happened to a listing. Soft deletes are reported as [`ListingAction::Deleted`] even
+/// though the record is only updated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ListingAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A change to a listing, as streamed by [`SubscribeListings::live_listings`]
+///
+/// [`SubscribeListings::live_listings`]: crate::api::SubscribeListings::live_listings
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ListingEvent {
+    pub action: ListingAction,
+    /// The listing as it is after the change
+    pub listing: Listing,
+}
+
+impl ListingEvent {
+    pub fn new(action: ListingAction, listing: Listing) -> Self {
+        Self { action, listing }
+    }
+}
+
+/// A change to the price of a listing
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct PriceChange {
+    pub previous: Decimal,
+    pub current: Decimal,
+}
+
+/// A change to the status of a listing
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct StatusChange {
+    pub previous: ListingStatus,
+    pub current: ListingStatus,
+}
+
+/// A change to the quantity a listing is sold in
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct QuantityChange {
+    pub previous: usize,
+    pub current: usize,
+}
+
+fn changed<T: PartialEq>(previous: T, current: T) -> Option<(T, T)> {
+    (previous != current).then_some((previous, current))
+}
+
+/// The fields of a listing buyers watching it are told about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatchedListing {
+    pub price: Decimal,
+    pub status: ListingStatus,
+    pub quantity: usize,
+}
+
+impl WatchedListing {
+    pub fn new(listing: &Listing, quantity: usize) -> Self {
+        Self {
+            price: listing.price,
+            status: listing.status,
+            quantity,
+        }
+    }
+
+    /// The fields that changed since `previous`, if any did
+    pub fn diff(&self, previous: &Self) -> Option<ListingDiff> {
+        let diff = ListingDiff {
+            price: changed(previous.price, self.price)
+                .map(|(previous, current)| PriceChange { previous, current }),
+            status: changed(previous.status, self.status)
+                .map(|(previous, current)| StatusChange { previous, current }),
+            quantity: changed(previous.quantity, self.quantity)
+                .map(|(previous, current)| QuantityChange { previous, current }),
+        };
+
+        (diff != ListingDiff::default()).then_some(diff)
+    }
+}
+
+/// The watched fields that changed on a listing. Fields that did not change are left unset
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct ListingDiff {
+    pub price: Option<PriceChange>,
+    pub status: Option<StatusChange>,
+    pub quantity: Option<QuantityChange>,
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use async_graphql::{Context, SimpleObject};
+use uuid::Uuid;
+
+use crate::{api::ListingEdgeSource, ListingCondition, ListingEdges};
+
+/// A reference to a user owned by the users service, which resolves the rest of its fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SimpleObject)]
+#[graphql(unresolvable)]
+pub struct User {
+    pub id: Uuid,
+}
+
+/// A reference to a category owned by the categories service, which resolves the rest of its
+/// fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SimpleObject)]
+#[graphql(unresolvable)]
+pub struct Category {
+    pub id: Uuid,
+}
+
+/// Loads the edges of a listing through the source attached to the schema data
+pub(crate) async fn listing_edges(
+    ctx: &Context<'_>,
+    listing_id: &Uuid,
+) -> async_graphql::Result<Option<ListingEdges>> {
+    let source = ctx.data::<Arc<dyn ListingEdgeSource>>()?;
+
+    source.listing_edges(listing_id).await
+}
+
+/// Loads the condition a listing's `withCondition` edge points to
+pub(crate) async fn listing_condition(
+    ctx: &Context<'_>,
+    listing_id: &Uuid,
+) -> async_graphql::Result<Option<ListingCondition>> {
+    let source = ctx.data::<Arc<dyn ListingEdgeSource>>()?;
+
+    source.listing_condition(listing_id).await
+}
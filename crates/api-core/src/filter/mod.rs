@@ -0,0 +1,146 @@
+use std::cmp::Ordering;
+
+#[cfg(feature = "async-graphql")]
+use async_graphql::{Enum, InputObject};
+use rust_decimal::Decimal;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{Listing, ListingStatus};
+
+/// Narrows down the listings returned by [`QueryListings::find_listings`]. Every field that is
+/// set must match, unset fields match everything
+///
+/// [`QueryListings::find_listings`]: crate::api::QueryListings::find_listings
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(InputObject))]
+pub struct ListingFilter {
+    pub category_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub condition_id: Option<Uuid>,
+    /// Matches listings carrying any of these tags
+    #[cfg_attr(feature = "async-graphql", graphql(default))]
+    pub tags: Vec<Uuid>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    pub negotiable: Option<bool>,
+    pub status: Option<ListingStatus>,
+    pub created_after: Option<OffsetDateTime>,
+    pub created_before: Option<OffsetDateTime>,
+    pub expires_after: Option<OffsetDateTime>,
+    pub expires_before: Option<OffsetDateTime>,
+}
+
+impl ListingFilter {
+    /// Whether a listing's own fields match. Relations (category, seller, condition and tags)
+    /// live outside of [`Listing`] and are left to the storage backend
+    pub fn matches(&self, listing: &Listing) -> bool {
+        let within = |value: Option<OffsetDateTime>,
+                      after: Option<OffsetDateTime>,
+                      before: Option<OffsetDateTime>| {
+            match value {
+                Some(value) => {
+                    after.map_or(true, |after| value >= after)
+                        && before.map_or(true, |before| value <= before)
+                }
+                None => after.is_none() && before.is_none(),
+            }
+        };
+
+        self.min_price.map_or(true, |min| listing.price >= min)
+            && self.max_price.map_or(true, |max| listing.price <= max)
+            && self
+                .negotiable
+                .map_or(true, |negotiable| listing.negotiable == negotiable)
+            && self.status.map_or(true, |status| listing.status == status)
+            && within(
+                Some(listing.created),
+                self.created_after,
+                self.created_before,
+            )
+            && within(listing.expires, self.expires_after, self.expires_before)
+    }
+}
+
+/// The order listings are returned in. Ties are broken by id so the order is total
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(Enum))]
+pub enum ListingSort {
+    #[default]
+    CreatedDesc,
+    CreatedAsc,
+    PriceAsc,
+    PriceDesc,
+    ExpiresAsc,
+}
+
+/// The value a [`ListingSort`] orders listings by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortValue {
+    Created(OffsetDateTime),
+    Price(Decimal),
+    Expires(Option<OffsetDateTime>),
+}
+
+/// Where a listing sits in a [`ListingSort`] order: the value it is sorted by and its id, which
+/// breaks ties. Unlike an index, a key stays put when other listings are added or removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingKey {
+    pub value: SortValue,
+    pub id: Uuid,
+}
+
+impl ListingSort {
+    /// The key of a listing in this order
+    pub fn key(&self, listing: &Listing) -> ListingKey {
+        let value = match self {
+            Self::CreatedDesc | Self::CreatedAsc => SortValue::Created(listing.created),
+            Self::PriceAsc | Self::PriceDesc => SortValue::Price(listing.price),
+            Self::ExpiresAsc => SortValue::Expires(listing.expires),
+        };
+
+        ListingKey {
+            value,
+            id: listing.id,
+        }
+    }
+
+    /// Whether a key was made for this order
+    pub fn accepts(&self, key: &ListingKey) -> bool {
+        matches!(
+            (self, key.value),
+            (Self::CreatedDesc | Self::CreatedAsc, SortValue::Created(_))
+                | (Self::PriceAsc | Self::PriceDesc, SortValue::Price(_))
+                | (Self::ExpiresAsc, SortValue::Expires(_))
+        )
+    }
+
+    /// Orders two keys made for this order. Values of keys made for another order are not
+    /// compared, leaving only their ids
+    pub fn compare_keys(&self, a: &ListingKey, b: &ListingKey) -> Ordering {
+        let ordering = match (self, a.value, b.value) {
+            (Self::CreatedDesc, SortValue::Created(a), SortValue::Created(b)) => b.cmp(&a),
+            (Self::CreatedAsc, SortValue::Created(a), SortValue::Created(b)) => a.cmp(&b),
+            (Self::PriceAsc, SortValue::Price(a), SortValue::Price(b)) => a.cmp(&b),
+            (Self::PriceDesc, SortValue::Price(a), SortValue::Price(b)) => b.cmp(&a),
+            // listings that never expire come last
+            (Self::ExpiresAsc, SortValue::Expires(a), SortValue::Expires(b)) => match (a, b) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+            _ => Ordering::Equal,
+        };
+
+        ordering.then_with(|| a.id.cmp(&b.id))
+    }
+
+    pub fn compare(&self, a: &Listing, b: &Listing) -> Ordering {
+        self.compare_keys(&self.key(a), &self.key(b))
+    }
+}
@@ -1,5 +1,28 @@
 pub mod api;
+mod event;
+#[cfg(feature = "async-graphql")]
+mod federation;
+mod filter;
+#[cfg(feature = "in-memory")]
+pub mod memory;
+mod node;
+mod page;
+mod webhook;
+
+pub use event::{
+    ListingAction, ListingDiff, ListingEvent, PriceChange, QuantityChange, StatusChange,
+    WatchedListing,
+};
+#[cfg(feature = "async-graphql")]
+pub use federation::{Category, User};
+pub use filter::{ListingFilter, ListingKey, ListingSort, SortValue};
+pub use node::{GlobalId, InvalidGlobalId, NodeType};
+pub use page::{Page, PageRequest};
+pub use webhook::{is_internal_ip, DeliveryStatus, Webhook, WebhookDelivery, WebhookEvent};
+
+use std::fmt;
 
+use api::CoreError;
 #[cfg(feature = "async-graphql")]
 use async_graphql::*;
 
@@ -12,17 +35,22 @@ use uuid::Uuid;
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "async-graphql", derive(InputObject, SimpleObject))]
-#[cfg_attr(feature = "async-graphql", graphql(input_name = "ListingInput"))]
+#[cfg_attr(
+    feature = "async-graphql",
+    graphql(input_name = "ListingInput", complex)
+)]
 pub struct Listing {
     #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
     pub id: Uuid,
     pub title: String,
     pub description: String,
+    #[cfg_attr(feature = "serde", serde(with = "rust_decimal::serde::str"))]
     pub price: Decimal,
     pub image_url: String,
     #[cfg_attr(feature = "async-graphql", graphql(default))]
     pub other_images: Vec<String>,
-    pub published: bool,
+    #[cfg_attr(feature = "async-graphql", graphql(default))]
+    pub status: ListingStatus,
     #[cfg_attr(feature = "async-graphql", graphql(default))]
     pub negotiable: bool,
     #[cfg_attr(
@@ -39,14 +67,149 @@ pub struct Listing {
     pub deleted: Option<OffsetDateTime>,
 }
 
+/// Where a listing is in its lifecycle.
+///
+/// Listings are created as [`ListingStatus::Draft`] or [`ListingStatus::Active`] and only move
+/// between statuses along the paths allowed by [`ListingStatus::can_transition_to`].
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[cfg_attr(feature = "async-graphql", derive(Enum))]
+pub enum ListingStatus {
+    #[default]
+    Draft,
+    Active,
+    Reserved,
+    Sold,
+    Expired,
+    Withdrawn,
+    Removed,
+}
+
+impl ListingStatus {
+    /// Whether a listing may be created with this status
+    pub fn is_initial(&self) -> bool {
+        matches!(self, Self::Draft | Self::Active)
+    }
+
+    /// Whether a listing in this status may move to `next`. [`ListingStatus::Removed`] is
+    /// terminal and sold listings can only be taken down
+    pub fn can_transition_to(&self, next: ListingStatus) -> bool {
+        use ListingStatus::*;
+
+        matches!(
+            (self, next),
+            (Draft, Active | Withdrawn | Removed)
+                | (Active, Reserved | Sold | Expired | Withdrawn | Removed)
+                | (Reserved, Active | Sold | Withdrawn | Removed)
+                | (Expired, Active | Withdrawn | Removed)
+                | (Withdrawn, Draft | Active | Removed)
+                | (Sold, Removed)
+        )
+    }
+
+    /// Returns `next` if the move is allowed, otherwise [`CoreError::InvalidTransition`]
+    pub fn transition_to(&self, next: ListingStatus) -> Result<ListingStatus, CoreError> {
+        if self.can_transition_to(next) {
+            Ok(next)
+        } else {
+            Err(CoreError::InvalidTransition {
+                from: *self,
+                to: next,
+            })
+        }
+    }
+}
+
+impl fmt::Display for ListingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            Self::Draft => "draft",
+            Self::Active => "active",
+            Self::Reserved => "reserved",
+            Self::Sold => "sold",
+            Self::Expired => "expired",
+            Self::Withdrawn => "withdrawn",
+            Self::Removed => "removed",
+        };
+        f.write_str(status)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "async-graphql", derive(InputObject, SimpleObject))]
+#[cfg_attr(feature = "async-graphql", graphql(complex))]
 pub struct ListingCondition {
     pub id: Uuid,
     pub condition: String,
 }
 
+#[cfg(feature = "async-graphql")]
+#[ComplexObject]
+impl Listing {
+    /// Globally unique id, used to refetch the listing through `node`
+    pub async fn global_id(&self) -> ID {
+        GlobalId::new(NodeType::Listing, self.id).to_string().into()
+    }
+
+    /// The user selling the listing, resolved by the users service
+    async fn seller(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        let edges = federation::listing_edges(ctx, &self.id).await?;
+        Ok(edges.map(|edges| User { id: edges.user_id }))
+    }
+
+    /// The category the listing is in, resolved by the categories service
+    async fn category(&self, ctx: &Context<'_>) -> Result<Option<Category>> {
+        let edges = federation::listing_edges(ctx, &self.id).await?;
+        Ok(edges.map(|edges| Category {
+            id: edges.category_id,
+        }))
+    }
+
+    async fn seller_id(&self, ctx: &Context<'_>) -> Result<Option<Uuid>> {
+        let edges = federation::listing_edges(ctx, &self.id).await?;
+        Ok(edges.map(|edges| edges.user_id))
+    }
+
+    async fn category_id(&self, ctx: &Context<'_>) -> Result<Option<Uuid>> {
+        let edges = federation::listing_edges(ctx, &self.id).await?;
+        Ok(edges.map(|edges| edges.category_id))
+    }
+
+    /// How many items the seller has for sale
+    async fn quantity(&self, ctx: &Context<'_>) -> Result<Option<usize>> {
+        let edges = federation::listing_edges(ctx, &self.id).await?;
+        Ok(edges.map(|edges| edges.quantity))
+    }
+
+    /// The condition the items are in
+    async fn condition(&self, ctx: &Context<'_>) -> Result<Option<ListingCondition>> {
+        federation::listing_condition(ctx, &self.id).await
+    }
+}
+
+#[cfg(feature = "async-graphql")]
+#[ComplexObject]
+impl ListingCondition {
+    /// Globally unique id, used to refetch the condition through `node`
+    pub async fn global_id(&self) -> ID {
+        GlobalId::new(NodeType::ListingCondition, self.id)
+            .to_string()
+            .into()
+    }
+}
+
+/// The graph edges a listing was created with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingEdges {
+    pub user_id: Uuid,
+    pub category_id: Uuid,
+    pub condition_id: Uuid,
+    /// How many items the seller has for sale, stored on the `sells` edge
+    pub quantity: usize,
+}
+
 #[cfg(feature = "async-graphql")]
 fn default_date_time() -> OffsetDateTime {
     OffsetDateTime::now_utc()
@@ -0,0 +1,591 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Debug,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::Duration,
+};
+
+use futures_channel::mpsc::{self, UnboundedSender};
+use futures_core::Stream;
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        CoreError, ManageWebhooks, MutateListings, QueryListingCondition, QueryListings,
+        SubscribeListings,
+    },
+    Listing, ListingAction, ListingCondition, ListingEdges, ListingEvent, ListingFilter,
+    ListingSort, ListingStatus, Page, PageRequest, Webhook, WebhookDelivery, WebhookEvent,
+};
+
+const POISONED: &str = "in-memory store lock poisoned";
+
+#[derive(Debug, Clone)]
+struct StoredListing {
+    listing: Listing,
+    user_id: Uuid,
+    category_id: Uuid,
+    condition_id: Uuid,
+    quantity: usize,
+    tags: Vec<Uuid>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    listings: BTreeMap<Uuid, StoredListing>,
+    conditions: BTreeMap<Uuid, ListingCondition>,
+    users: Option<HashSet<Uuid>>,
+    categories: Option<HashSet<Uuid>>,
+    images: HashMap<String, Vec<u8>>,
+    /// Live subscribers, along with the only listing they watch if they watch a single one
+    subscribers: Vec<(Option<Uuid>, UnboundedSender<ListingEvent>)>,
+    webhooks: BTreeMap<Uuid, Webhook>,
+}
+
+impl StoredListing {
+    fn matches(&self, filter: &ListingFilter) -> bool {
+        filter.category_id.map_or(true, |id| id == self.category_id)
+            && filter.user_id.map_or(true, |id| id == self.user_id)
+            && filter
+                .condition_id
+                .map_or(true, |id| id == self.condition_id)
+            && (filter.tags.is_empty() || self.tags.iter().any(|tag| filter.tags.contains(tag)))
+            && filter.matches(&self.listing)
+    }
+}
+
+impl State {
+    /// Pushes a changed listing to every live subscriber, dropping the ones that went away
+    fn notify(&mut self, action: ListingAction, listing: &Listing) {
+        let event = ListingEvent::new(action, listing.clone());
+        self.subscribers.retain(|(watched, subscriber)| {
+            watched.is_some_and(|id| id != listing.id)
+                || subscriber.unbounded_send(event.clone()).is_ok()
+        });
+    }
+}
+
+/// A storage backend that keeps every record in process memory.
+///
+/// Cloning is cheap and clones share the same underlying data. Users and categories are
+/// accepted as-is unless a known set is registered with [`InMemoryStore::with_users`] or
+/// [`InMemoryStore::with_categories`], in which case listings referencing unknown ids are
+/// rejected just like they would be by the users and categories services.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    state: Arc<RwLock<State>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_users(&mut self, users: impl IntoIterator<Item = Uuid>) {
+        self.state.write().expect(POISONED).users = Some(users.into_iter().collect());
+    }
+
+    pub fn with_categories(&mut self, categories: impl IntoIterator<Item = Uuid>) {
+        self.state.write().expect(POISONED).categories = Some(categories.into_iter().collect());
+    }
+
+    pub fn with_conditions(&mut self, conditions: impl IntoIterator<Item = ListingCondition>) {
+        self.state.write().expect(POISONED).conditions = conditions
+            .into_iter()
+            .map(|condition| (condition.id, condition))
+            .collect();
+    }
+
+    /// Associates tags with an existing listing so it can be found by
+    /// [`QueryListings::get_listings_with_tags`]
+    pub fn tag_listing(&self, listing_id: &Uuid, tags: &[Uuid]) -> Result<(), CoreError> {
+        let mut state = self.write()?;
+        match state.listings.get_mut(listing_id) {
+            Some(stored) => {
+                stored.tags = tags.to_vec();
+                Ok(())
+            }
+            None => Err(CoreError::Database(format!(
+                "listing: {listing_id} does not exist"
+            ))),
+        }
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, State>, CoreError> {
+        self.state
+            .read()
+            .map_err(|e| CoreError::Database(e.to_string()))
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, State>, CoreError> {
+        self.state
+            .write()
+            .map_err(|e| CoreError::Database(e.to_string()))
+    }
+
+    fn collect_listings(
+        &self,
+        predicate: impl Fn(&StoredListing) -> bool,
+    ) -> Result<std::vec::IntoIter<Listing>, CoreError> {
+        let state = self.read()?;
+        let listings: Vec<Listing> = state
+            .listings
+            .values()
+            .filter(|stored| stored.listing.deleted.is_none() && predicate(stored))
+            .map(|stored| stored.listing.clone())
+            .collect();
+
+        Ok(listings.into_iter())
+    }
+}
+
+fn check_listing_validity(
+    state: &State,
+    category_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<(), CoreError> {
+    if let Some(ref categories) = state.categories {
+        if !categories.contains(category_id) {
+            return Err(CoreError::Database(format!(
+                "category: {} does not exist",
+                category_id
+            )));
+        }
+    }
+
+    if let Some(ref users) = state.users {
+        if !users.contains(user_id) {
+            return Err(CoreError::Database(format!(
+                "user: {} does not exist",
+                user_id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Looks up a listing that has not been deleted
+fn live_listing<'a>(state: &'a mut State, id: &Uuid) -> Option<&'a mut StoredListing> {
+    state
+        .listings
+        .get_mut(id)
+        .filter(|stored| stored.listing.deleted.is_none())
+}
+
+fn check_listing_owner(stored: &StoredListing, user_id: &Uuid) -> Result<(), CoreError> {
+    if stored.user_id == *user_id {
+        Ok(())
+    } else {
+        Err(CoreError::Forbidden(format!(
+            "user: {} does not sell listing: {}",
+            user_id, stored.listing.id
+        )))
+    }
+}
+
+/// Looks up a listing, deleted or not, that the user sells
+fn owned_listing<'a>(
+    state: &'a mut State,
+    id: &Uuid,
+    user_id: &Uuid,
+) -> Result<&'a mut StoredListing, CoreError> {
+    let stored = state
+        .listings
+        .get_mut(id)
+        .ok_or_else(|| CoreError::Database(format!("listing: {id} does not exist")))?;
+    check_listing_owner(stored, user_id)?;
+
+    Ok(stored)
+}
+
+impl QueryListings for InMemoryStore {
+    async fn get_listings(&self) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        self.collect_listings(|_| true)
+    }
+
+    async fn get_listing_by_id(&self, listing_id: &Uuid) -> Result<Option<Listing>, CoreError> {
+        let state = self.read()?;
+        Ok(state
+            .listings
+            .get(listing_id)
+            .filter(|stored| stored.listing.deleted.is_none())
+            .map(|stored| stored.listing.clone()))
+    }
+
+    async fn get_listings_by_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        self.collect_listings(|stored| listing_ids.contains(&stored.listing.id))
+    }
+
+    async fn get_listing_owner(&self, listing_id: &Uuid) -> Result<Option<Uuid>, CoreError> {
+        let state = self.read()?;
+        Ok(state.listings.get(listing_id).map(|stored| stored.user_id))
+    }
+
+    async fn get_edges_by_listing_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, ListingEdges>, CoreError> {
+        let state = self.read()?;
+        Ok(listing_ids
+            .iter()
+            .filter_map(|id| state.listings.get(id))
+            .map(|stored| {
+                let edges = ListingEdges {
+                    user_id: stored.user_id,
+                    category_id: stored.category_id,
+                    condition_id: stored.condition_id,
+                    quantity: stored.quantity,
+                };
+                (stored.listing.id, edges)
+            })
+            .collect())
+    }
+
+    async fn get_listings_from_user(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        self.collect_listings(|stored| stored.user_id == *user_id)
+    }
+
+    async fn get_listings_in_category(
+        &self,
+        category_id: &Uuid,
+    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        self.collect_listings(|stored| stored.category_id == *category_id)
+    }
+
+    async fn get_listings_with_tags(
+        &self,
+        tags: &[&Uuid],
+    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        self.collect_listings(|stored| stored.tags.iter().any(|tag| tags.contains(&tag)))
+    }
+
+    async fn get_listings_in_price_range(
+        &self,
+        min: &Decimal,
+        max: &Decimal,
+    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        self.collect_listings(|stored| stored.listing.price >= *min && stored.listing.price <= *max)
+    }
+
+    async fn search(
+        &self,
+        query: impl AsRef<str> + Send + Debug,
+        status: ListingStatus,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Page<Listing>, CoreError> {
+        let query = query.as_ref().to_lowercase();
+        let terms: Vec<&str> = query.split_whitespace().collect();
+
+        let listings = self.collect_listings(|stored| {
+            let title = stored.listing.title.to_lowercase();
+            let description = stored.listing.description.to_lowercase();
+            stored.listing.status == status
+                && terms
+                    .iter()
+                    .any(|term| title.contains(term) || description.contains(term))
+        })?;
+
+        Ok(Page {
+            total: listings.len(),
+            items: listings.skip(offset).take(limit).collect(),
+        })
+    }
+
+    async fn find_listings(
+        &self,
+        filter: &ListingFilter,
+        sort: ListingSort,
+        page: PageRequest,
+    ) -> Result<Page<Listing>, CoreError> {
+        let mut listings: Vec<_> = self
+            .collect_listings(|stored| stored.matches(filter))?
+            .collect();
+        listings.sort_by(|a, b| sort.compare(a, b));
+
+        Ok(page.slice(sort, listings))
+    }
+}
+
+impl QueryListingCondition for InMemoryStore {
+    async fn get_conditions(
+        &self,
+    ) -> Result<impl ExactSizeIterator<Item = ListingCondition>, CoreError> {
+        let state = self.read()?;
+        let conditions: Vec<ListingCondition> = state.conditions.values().cloned().collect();
+
+        Ok(conditions.into_iter())
+    }
+
+    async fn get_conditions_by_listing_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, ListingCondition>, CoreError> {
+        let state = self.read()?;
+
+        Ok(listing_ids
+            .iter()
+            .filter_map(|id| state.listings.get(id))
+            .filter_map(|stored| {
+                let condition = state.conditions.get(&stored.condition_id)?;
+                Some((stored.listing.id, condition.clone()))
+            })
+            .collect())
+    }
+}
+
+impl MutateListings for InMemoryStore {
+    async fn create_listing(
+        &self,
+        listing: &Listing,
+        user_id: &Uuid,
+        category_id: &Uuid,
+        condition_id: &Uuid,
+        quantity: usize,
+    ) -> Result<Listing, CoreError> {
+        if !listing.status.is_initial() {
+            return Err(CoreError::InvalidTransition {
+                from: ListingStatus::Draft,
+                to: listing.status,
+            });
+        }
+
+        let mut state = self.write()?;
+        check_listing_validity(&state, category_id, user_id)?;
+
+        let now = OffsetDateTime::now_utc();
+        let listing = Listing {
+            id: Uuid::now_v7(),
+            created: now,
+            updated: now,
+            deleted: None,
+            ..listing.clone()
+        };
+
+        state.listings.insert(
+            listing.id,
+            StoredListing {
+                listing: listing.clone(),
+                user_id: *user_id,
+                category_id: *category_id,
+                condition_id: *condition_id,
+                quantity,
+                tags: Vec::new(),
+            },
+        );
+        state.notify(ListingAction::Created, &listing);
+
+        Ok(listing)
+    }
+
+    /// Like the database, the fields of the listing and its quantity are changed: its category
+    /// and condition stay as they were created
+    async fn update_listing(
+        &self,
+        id: &Uuid,
+        data: &Listing,
+        user_id: &Uuid,
+        category_id: &Uuid,
+        _condition_id: &Uuid,
+        quantity: usize,
+    ) -> Result<Option<Listing>, CoreError> {
+        let mut state = self.write()?;
+        check_listing_validity(&state, category_id, user_id)?;
+
+        let stored = owned_listing(&mut state, id, user_id)?;
+        if stored.listing.deleted.is_some() {
+            return Ok(None);
+        }
+        stored.listing = Listing {
+            id: *id,
+            created: stored.listing.created,
+            updated: OffsetDateTime::now_utc(),
+            status: stored.listing.status,
+            deleted: None,
+            ..data.clone()
+        };
+        stored.quantity = quantity;
+        let listing = stored.listing.clone();
+        state.notify(ListingAction::Updated, &listing);
+
+        Ok(Some(listing))
+    }
+
+    async fn delete_listing(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Option<Listing>, CoreError> {
+        let mut state = self.write()?;
+
+        let stored = owned_listing(&mut state, id, user_id)?;
+        if stored.listing.deleted.is_some() {
+            return Ok(None);
+        }
+        let now = OffsetDateTime::now_utc();
+        stored.listing.deleted = Some(now);
+        stored.listing.updated = now;
+        let listing = stored.listing.clone();
+        state.notify(ListingAction::Deleted, &listing);
+
+        Ok(Some(listing))
+    }
+
+    async fn restore_listing(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Option<Listing>, CoreError> {
+        let mut state = self.write()?;
+
+        let stored = owned_listing(&mut state, id, user_id)?;
+        if stored.listing.deleted.is_none() {
+            return Ok(None);
+        }
+        stored.listing.deleted = None;
+        stored.listing.updated = OffsetDateTime::now_utc();
+        let listing = stored.listing.clone();
+        state.notify(ListingAction::Updated, &listing);
+
+        Ok(Some(listing))
+    }
+
+    async fn purge_deleted_listings(&self, retention: Duration) -> Result<usize, CoreError> {
+        let mut state = self.write()?;
+        let cutoff = OffsetDateTime::now_utc() - retention;
+
+        let expired: Vec<Uuid> = state
+            .listings
+            .values()
+            .filter(|stored| matches!(stored.listing.deleted, Some(deleted) if deleted <= cutoff))
+            .map(|stored| stored.listing.id)
+            .collect();
+
+        for id in expired.iter() {
+            if let Some(stored) = state.listings.remove(id) {
+                let listing = stored.listing;
+                for image in std::iter::once(&listing.image_url).chain(listing.other_images.iter())
+                {
+                    state.images.remove(image);
+                }
+                state.notify(ListingAction::Deleted, &listing);
+            }
+        }
+
+        Ok(expired.len())
+    }
+
+    /// Changes are applied to the store as they are made, so nothing is ever left to deliver
+    async fn dispatch_outbox(&self, _batch: usize) -> Result<usize, CoreError> {
+        Ok(0)
+    }
+
+    async fn transition_listing(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+        status: ListingStatus,
+    ) -> Result<Option<Listing>, CoreError> {
+        let mut state = self.write()?;
+
+        let listing = match live_listing(&mut state, id) {
+            Some(stored) => {
+                check_listing_owner(stored, user_id)?;
+                stored.listing.status = stored.listing.status.transition_to(status)?;
+                stored.listing.updated = OffsetDateTime::now_utc();
+
+                stored.listing.clone()
+            }
+            None => return Ok(None),
+        };
+        state.notify(ListingAction::Updated, &listing);
+
+        Ok(Some(listing))
+    }
+
+    async fn upload_images(&self, files: &[&[u8]]) -> Result<Vec<(String, String)>, CoreError> {
+        let mut state = self.write()?;
+
+        Ok(files
+            .iter()
+            .map(|file| {
+                let id = Uuid::now_v7().to_string();
+                state.images.insert(id.clone(), file.to_vec());
+                (id.clone(), id)
+            })
+            .collect())
+    }
+}
+
+impl SubscribeListings for InMemoryStore {
+    async fn live_listings(
+        &self,
+    ) -> Result<impl Stream<Item = ListingEvent> + Send + '_, CoreError> {
+        let (tx, rx) = mpsc::unbounded();
+        self.write()?.subscribers.push((None, tx));
+
+        Ok(rx)
+    }
+
+    async fn watch_listing(
+        &self,
+        listing_id: Uuid,
+    ) -> Result<impl Stream<Item = ListingEvent> + Send + '_, CoreError> {
+        let (tx, rx) = mpsc::unbounded();
+        self.write()?.subscribers.push((Some(listing_id), tx));
+
+        Ok(rx)
+    }
+}
+
+/// Webhooks are kept so they can be managed, but nothing is ever posted to them, so their
+/// delivery log stays empty
+impl ManageWebhooks for InMemoryStore {
+    async fn create_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+    ) -> Result<Webhook, CoreError> {
+        let webhook = Webhook {
+            id: Uuid::now_v7(),
+            url: url.to_owned(),
+            secret: secret.to_owned(),
+            events: events.to_vec(),
+            created: OffsetDateTime::now_utc(),
+        };
+        self.write()?.webhooks.insert(webhook.id, webhook.clone());
+
+        Ok(webhook)
+    }
+
+    async fn delete_webhook(&self, id: &Uuid) -> Result<Option<Webhook>, CoreError> {
+        Ok(self.write()?.webhooks.remove(id))
+    }
+
+    async fn get_webhooks(&self) -> Result<impl ExactSizeIterator<Item = Webhook>, CoreError> {
+        let webhooks: Vec<Webhook> = self.read()?.webhooks.values().cloned().collect();
+        Ok(webhooks.into_iter())
+    }
+
+    async fn get_webhook_deliveries(
+        &self,
+        _webhook_id: &Uuid,
+        _limit: usize,
+    ) -> Result<impl ExactSizeIterator<Item = WebhookDelivery>, CoreError> {
+        Ok(Vec::<WebhookDelivery>::new().into_iter())
+    }
+
+    async fn dispatch_webhooks(&self, _batch: usize) -> Result<usize, CoreError> {
+        Ok(0)
+    }
+}
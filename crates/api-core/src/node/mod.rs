@@ -0,0 +1,76 @@
+use std::{fmt, str::FromStr};
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// The kinds of objects that can be refetched by their [`GlobalId`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeType {
+    Listing,
+    ListingCondition,
+}
+
+impl fmt::Display for NodeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let node_type = match self {
+            Self::Listing => "Listing",
+            Self::ListingCondition => "ListingCondition",
+        };
+        f.write_str(node_type)
+    }
+}
+
+impl FromStr for NodeType {
+    type Err = InvalidGlobalId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Listing" => Ok(Self::Listing),
+            "ListingCondition" => Ok(Self::ListingCondition),
+            _ => Err(InvalidGlobalId(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("invalid global id: {0}")]
+pub struct InvalidGlobalId(String);
+
+/// An id that is unique across every type, made of the type name and the object's own id. It
+/// is encoded as an opaque base64 string of `Type:uuid`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlobalId {
+    pub node_type: NodeType,
+    pub id: Uuid,
+}
+
+impl GlobalId {
+    pub fn new(node_type: NodeType, id: Uuid) -> Self {
+        Self { node_type, id }
+    }
+}
+
+impl fmt::Display for GlobalId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = BASE64_URL_SAFE_NO_PAD.encode(format!("{}:{}", self.node_type, self.id));
+        f.write_str(&id)
+    }
+}
+
+impl FromStr for GlobalId {
+    type Err = InvalidGlobalId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidGlobalId(s.to_owned());
+
+        let bytes = BASE64_URL_SAFE_NO_PAD.decode(s).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (node_type, id) = decoded.split_once(':').ok_or_else(invalid)?;
+
+        Ok(Self {
+            node_type: node_type.parse().map_err(|_| invalid())?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
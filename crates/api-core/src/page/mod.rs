@@ -0,0 +1,83 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Listing, ListingKey, ListingSort};
+
+/// A window into an ordered set of listings. The window is bounded by the keys of the listings
+/// on either side of it rather than by positions, so it does not shift when listings are added
+/// or removed between requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Only listings that come after this key
+    pub after: Option<ListingKey>,
+    /// Only listings that come before this key
+    pub before: Option<ListingKey>,
+    /// The most items to return
+    pub limit: usize,
+    /// Whether to return the last `limit` listings of the window instead of the first
+    pub from_end: bool,
+}
+
+impl PageRequest {
+    /// The first `limit` listings
+    pub fn first(limit: usize) -> Self {
+        Self {
+            after: None,
+            before: None,
+            limit,
+            from_end: false,
+        }
+    }
+
+    /// The last `limit` listings
+    pub fn last(limit: usize) -> Self {
+        Self {
+            from_end: true,
+            ..Self::first(limit)
+        }
+    }
+
+    pub fn with_after(mut self, key: Option<ListingKey>) -> Self {
+        self.after = key;
+        self
+    }
+
+    pub fn with_before(mut self, key: Option<ListingKey>) -> Self {
+        self.before = key;
+        self
+    }
+
+    /// Whether a key falls within the window's bounds
+    pub fn contains(&self, sort: ListingSort, key: &ListingKey) -> bool {
+        self.after
+            .map_or(true, |after| sort.compare_keys(key, &after).is_gt())
+            && self
+                .before
+                .map_or(true, |before| sort.compare_keys(key, &before).is_lt())
+    }
+
+    /// Applies the window to listings that were loaded in full and ordered by `sort`
+    pub fn slice(&self, sort: ListingSort, listings: Vec<Listing>) -> Page<Listing> {
+        let total = listings.len();
+        let mut items: Vec<_> = listings
+            .into_iter()
+            .filter(|listing| self.contains(sort, &sort.key(listing)))
+            .collect();
+
+        if self.from_end {
+            items.drain(..items.len().saturating_sub(self.limit));
+        } else {
+            items.truncate(self.limit);
+        }
+
+        Page { items, total }
+    }
+}
+
+/// One page of an ordered result set along with the size of the whole set
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+}
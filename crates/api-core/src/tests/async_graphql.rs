@@ -1,6 +1,6 @@
-use async_graphql::{EmptySubscription, Object, Schema};
+use async_graphql::{EmptySubscription, ErrorExtensions, Object, Schema};
 
-use crate::Listing;
+use crate::{api::CoreError, Listing};
 
 struct Root;
 
@@ -45,14 +45,11 @@ async fn gql_mutation() {
             r#"
               mutation {
                 input (listing: {
-                    userId: "7c503531-2900-4fb3-b4ac-203f7bb6ac2f",
-                    categoryId: "7c503531-2910-4fb3-b4ac-203f7bb6ac2f",
                     title: "Title",
                     description: "Desc",
                     price: 34.3,
                     imageUrl: "url",
-                    active: true,
-                    location: "",
+                    status: ACTIVE,
                 }) {
                   id
                 }
@@ -65,3 +62,13 @@ async fn gql_mutation() {
 
     assert!(res.errors.is_empty());
 }
+
+#[test]
+fn forbidden_error_code() {
+    let err = CoreError::Forbidden(String::from("not the owner")).extend();
+    let code = err.extensions.and_then(|ext| ext.get("code").cloned());
+    assert_eq!(code, Some(async_graphql::Value::from("FORBIDDEN")));
+
+    let err = CoreError::Unknown.extend();
+    assert!(err.extensions.is_none());
+}
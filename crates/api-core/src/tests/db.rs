@@ -1,10 +1,11 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug, time::Duration};
 
+use rust_decimal::Decimal;
 use uuid::Uuid;
 
 use crate::{
     api::{CoreError, LocalMutateListings, LocalQueryListings, MutateListings, QueryListings},
-    Listing,
+    Listing, ListingEdges, ListingFilter, ListingSort, ListingStatus, Page, PageRequest,
 };
 
 pub struct SampleDb;
@@ -19,6 +20,24 @@ impl LocalQueryListings for SampleDb {
         Ok(None)
     }
 
+    async fn get_listings_by_ids(
+        &self,
+        _listing_ids: &[Uuid],
+    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        Ok([].into_iter())
+    }
+
+    async fn get_listing_owner(&self, _listing_id: &Uuid) -> Result<Option<Uuid>, CoreError> {
+        Ok(None)
+    }
+
+    async fn get_edges_by_listing_ids(
+        &self,
+        _listing_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, ListingEdges>, CoreError> {
+        Ok(HashMap::new())
+    }
+
     async fn get_listings_from_user(
         &self,
         _user_id: &Uuid,
@@ -35,8 +54,8 @@ impl LocalQueryListings for SampleDb {
 
     async fn get_listings_in_price_range(
         &self,
-        _min: f32,
-        _max: f32,
+        _min: &Decimal,
+        _max: &Decimal,
     ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
         Ok([].into_iter())
     }
@@ -44,8 +63,23 @@ impl LocalQueryListings for SampleDb {
     async fn search(
         &self,
         _query: impl AsRef<str> + Send + Debug,
-    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
-        Ok([].into_iter())
+        _status: ListingStatus,
+        _offset: usize,
+        _limit: usize,
+    ) -> Result<Page<Listing>, CoreError> {
+        Ok(Page {
+            items: Vec::new(),
+            total: 0,
+        })
+    }
+
+    async fn find_listings(
+        &self,
+        _filter: &ListingFilter,
+        sort: ListingSort,
+        page: PageRequest,
+    ) -> Result<Page<Listing>, CoreError> {
+        Ok(page.slice(sort, vec![]))
     }
 
     async fn get_listings_with_tags(
@@ -61,6 +95,9 @@ impl LocalMutateListings for SampleDb {
         &self,
         listing: &Listing,
         _user_id: &Uuid,
+        _category_id: &Uuid,
+        _condition_id: &Uuid,
+        _quantity: usize,
     ) -> Result<Listing, CoreError> {
         Ok(listing.to_owned())
     }
@@ -70,6 +107,9 @@ impl LocalMutateListings for SampleDb {
         _id: &Uuid,
         data: &Listing,
         _user_id: &Uuid,
+        _category_id: &Uuid,
+        _condition_id: &Uuid,
+        _quantity: usize,
     ) -> Result<Option<Listing>, CoreError> {
         Ok(Some(data.to_owned()))
     }
@@ -82,6 +122,31 @@ impl LocalMutateListings for SampleDb {
         Ok(None)
     }
 
+    async fn restore_listing(
+        &self,
+        _id: &Uuid,
+        _user_id: &Uuid,
+    ) -> Result<Option<Listing>, CoreError> {
+        Ok(None)
+    }
+
+    async fn purge_deleted_listings(&self, _retention: Duration) -> Result<usize, CoreError> {
+        Ok(0)
+    }
+
+    async fn dispatch_outbox(&self, _batch: usize) -> Result<usize, CoreError> {
+        Ok(0)
+    }
+
+    async fn transition_listing(
+        &self,
+        _id: &Uuid,
+        _user_id: &Uuid,
+        _status: ListingStatus,
+    ) -> Result<Option<Listing>, CoreError> {
+        Ok(None)
+    }
+
     async fn upload_images(&self, _files: &[&[u8]]) -> Result<Vec<(String, String)>, CoreError> {
         Ok(Default::default())
     }
@@ -92,6 +157,9 @@ impl MutateListings for SampleDbSend {
         &self,
         listing: &Listing,
         _user_id: &Uuid,
+        _category_id: &Uuid,
+        _condition_id: &Uuid,
+        _quantity: usize,
     ) -> Result<Listing, CoreError> {
         Ok(listing.to_owned())
     }
@@ -101,6 +169,9 @@ impl MutateListings for SampleDbSend {
         _id: &Uuid,
         data: &Listing,
         _user_id: &Uuid,
+        _category_id: &Uuid,
+        _condition_id: &Uuid,
+        _quantity: usize,
     ) -> Result<Option<Listing>, CoreError> {
         Ok(Some(data.to_owned()))
     }
@@ -113,6 +184,31 @@ impl MutateListings for SampleDbSend {
         Ok(None)
     }
 
+    async fn restore_listing(
+        &self,
+        _id: &Uuid,
+        _user_id: &Uuid,
+    ) -> Result<Option<Listing>, CoreError> {
+        Ok(None)
+    }
+
+    async fn purge_deleted_listings(&self, _retention: Duration) -> Result<usize, CoreError> {
+        Ok(0)
+    }
+
+    async fn dispatch_outbox(&self, _batch: usize) -> Result<usize, CoreError> {
+        Ok(0)
+    }
+
+    async fn transition_listing(
+        &self,
+        _id: &Uuid,
+        _user_id: &Uuid,
+        _status: ListingStatus,
+    ) -> Result<Option<Listing>, CoreError> {
+        Ok(None)
+    }
+
     async fn upload_images(&self, _files: &[&[u8]]) -> Result<Vec<(String, String)>, CoreError> {
         Ok(Default::default())
     }
@@ -134,6 +230,24 @@ impl QueryListings for SampleDbSend {
         Ok(None)
     }
 
+    async fn get_listings_by_ids(
+        &self,
+        _listing_ids: &[Uuid],
+    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        Ok([].into_iter())
+    }
+
+    async fn get_listing_owner(&self, _listing_id: &Uuid) -> Result<Option<Uuid>, CoreError> {
+        Ok(None)
+    }
+
+    async fn get_edges_by_listing_ids(
+        &self,
+        _listing_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, ListingEdges>, CoreError> {
+        Ok(HashMap::new())
+    }
+
     async fn get_listings_from_user(
         &self,
         _user_id: &Uuid,
@@ -150,8 +264,8 @@ impl QueryListings for SampleDbSend {
 
     async fn get_listings_in_price_range(
         &self,
-        _min: f32,
-        _max: f32,
+        _min: &Decimal,
+        _max: &Decimal,
     ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
         Ok([].into_iter())
     }
@@ -159,7 +273,22 @@ impl QueryListings for SampleDbSend {
     async fn search(
         &self,
         _query: impl AsRef<str> + Send + Debug,
-    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
-        Ok([].into_iter())
+        _status: ListingStatus,
+        _offset: usize,
+        _limit: usize,
+    ) -> Result<Page<Listing>, CoreError> {
+        Ok(Page {
+            items: Vec::new(),
+            total: 0,
+        })
+    }
+
+    async fn find_listings(
+        &self,
+        _filter: &ListingFilter,
+        sort: ListingSort,
+        page: PageRequest,
+    ) -> Result<Page<Listing>, CoreError> {
+        Ok(page.slice(sort, vec![]))
     }
 }
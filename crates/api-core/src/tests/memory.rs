@@ -0,0 +1,498 @@
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    api::{CoreError, ManageWebhooks, MutateListings, QueryListingCondition, QueryListings},
+    memory::InMemoryStore,
+    Listing, ListingCondition, ListingFilter, ListingSort, ListingStatus, PageRequest,
+    WebhookEvent,
+};
+
+#[tokio::test]
+async fn memory_create_update_delete() {
+    let db = InMemoryStore::new();
+    let (user, category, condition) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+
+    let listing = Listing::default();
+    let created = db
+        .create_listing(&listing, &user, &category, &condition, 1)
+        .await
+        .unwrap();
+    assert_ne!(created.id, listing.id);
+    assert_eq!(created.title, listing.title);
+    assert_eq!(db.get_listings().await.unwrap().len(), 1);
+
+    let by_id = db.get_listing_by_id(&created.id).await.unwrap();
+    assert_eq!(by_id, Some(created.clone()));
+
+    let mut update = created.clone();
+    update.title = String::from("FooBar");
+    let updated = db
+        .update_listing(&created.id, &update, &user, &category, &condition, 2)
+        .await
+        .unwrap()
+        .expect("listing to exist");
+    assert_eq!(updated.id, created.id);
+    assert_eq!(updated.title, "FooBar");
+    assert_eq!(updated.created, created.created);
+    let edges = db.get_edges_by_listing_ids(&[created.id]).await.unwrap();
+    assert_eq!(edges[&created.id].quantity, 2);
+
+    let missing = db
+        .update_listing(&Uuid::now_v7(), &update, &user, &category, &condition, 2)
+        .await;
+    assert!(matches!(missing, Err(CoreError::Database(_))));
+
+    let deleted = db.delete_listing(&created.id, &user).await.unwrap();
+    assert_eq!(deleted.map(|listing| listing.id), Some(created.id));
+    assert!(db.get_listing_by_id(&created.id).await.unwrap().is_none());
+    assert!(db
+        .delete_listing(&created.id, &user)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn memory_validates_known_references() {
+    let mut db = InMemoryStore::new();
+    let (user, category, condition) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+    db.with_users([user]);
+    db.with_categories([category]);
+
+    let listing = Listing::default();
+    let res = db
+        .create_listing(&listing, &user, &Uuid::now_v7(), &condition, 1)
+        .await;
+    assert!(res.is_err()); // no category
+
+    let res = db
+        .create_listing(&listing, &Uuid::now_v7(), &category, &condition, 1)
+        .await;
+    assert!(res.is_err()); // no user
+
+    let res = db
+        .create_listing(&listing, &user, &category, &condition, 1)
+        .await;
+    assert!(res.is_ok());
+}
+
+#[tokio::test]
+async fn memory_queries() {
+    let db = InMemoryStore::new();
+    let (user, category, condition) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+
+    let cheap = Listing {
+        title: String::from("Vintage phone"),
+        price: Decimal::new(50, 0),
+        ..Default::default()
+    };
+    let cheap = db
+        .create_listing(&cheap, &user, &category, &condition, 1)
+        .await
+        .unwrap();
+
+    let pricey = Listing {
+        title: String::from("Gaming laptop"),
+        price: Decimal::new(900, 0),
+        ..Default::default()
+    };
+    let pricey = db
+        .create_listing(&pricey, &Uuid::now_v7(), &Uuid::now_v7(), &condition, 1)
+        .await
+        .unwrap();
+
+    let from_user: Vec<_> = db.get_listings_from_user(&user).await.unwrap().collect();
+    assert_eq!(from_user, vec![cheap.clone()]);
+
+    let in_category: Vec<_> = db
+        .get_listings_in_category(&category)
+        .await
+        .unwrap()
+        .collect();
+    assert_eq!(in_category, vec![cheap.clone()]);
+
+    let in_range: Vec<_> = db
+        .get_listings_in_price_range(&Decimal::new(100, 0), &Decimal::new(1000, 0))
+        .await
+        .unwrap()
+        .collect();
+    assert_eq!(in_range, vec![pricey.clone()]);
+
+    let search = db
+        .search("LAPTOP", ListingStatus::Active, 0, 10)
+        .await
+        .unwrap();
+    assert_eq!(search.items, vec![pricey.clone()]);
+    assert_eq!(search.total, 1);
+    let search = db
+        .search("phone laptop", ListingStatus::Active, 1, 10)
+        .await
+        .unwrap();
+    assert_eq!(search.items, vec![pricey.clone()]);
+    assert_eq!(search.total, 2);
+    let search = db
+        .search("laptop", ListingStatus::Draft, 0, 10)
+        .await
+        .unwrap();
+    assert!(search.items.is_empty());
+
+    let tag = Uuid::now_v7();
+    assert_eq!(db.get_listings_with_tags(&[&tag]).await.unwrap().len(), 0);
+    db.tag_listing(&pricey.id, &[tag]).unwrap();
+    let tagged: Vec<_> = db.get_listings_with_tags(&[&tag]).await.unwrap().collect();
+    assert_eq!(tagged, vec![pricey]);
+}
+
+#[tokio::test]
+async fn memory_conditions_and_uploads() {
+    let mut db = InMemoryStore::new();
+    assert_eq!(db.get_conditions().await.unwrap().len(), 0);
+
+    let condition = ListingCondition {
+        id: Uuid::now_v7(),
+        condition: String::from("New"),
+    };
+    db.with_conditions([condition.clone()]);
+    let conditions: Vec<_> = db.get_conditions().await.unwrap().collect();
+    assert_eq!(conditions, vec![condition]);
+
+    let uploaded = db.upload_images(&[b"first", b"second"]).await.unwrap();
+    assert_eq!(uploaded.len(), 2);
+}
+
+#[tokio::test]
+async fn memory_batch_lookups() {
+    let mut db = InMemoryStore::new();
+    let condition = ListingCondition {
+        id: Uuid::now_v7(),
+        condition: String::from("Used"),
+    };
+    db.with_conditions([condition.clone()]);
+    let (user, category) = (Uuid::now_v7(), Uuid::now_v7());
+
+    let kept = db
+        .create_listing(&Listing::default(), &user, &category, &condition.id, 2)
+        .await
+        .unwrap();
+    let deleted = db
+        .create_listing(&Listing::default(), &user, &category, &Uuid::now_v7(), 1)
+        .await
+        .unwrap();
+    db.delete_listing(&deleted.id, &user).await.unwrap();
+    let ids = [kept.id, deleted.id, Uuid::now_v7()];
+
+    let listings: Vec<_> = db.get_listings_by_ids(&ids).await.unwrap().collect();
+    assert_eq!(listings, vec![kept.clone()]);
+
+    let edges = db.get_edges_by_listing_ids(&ids).await.unwrap();
+    assert_eq!(edges.len(), 2);
+    assert_eq!(edges[&kept.id].quantity, 2);
+    assert_eq!(edges[&deleted.id].user_id, user);
+
+    // the deleted listing points to a condition that was never stored
+    let conditions = db.get_conditions_by_listing_ids(&ids).await.unwrap();
+    assert_eq!(conditions.len(), 1);
+    assert_eq!(conditions[&kept.id], condition);
+}
+
+#[tokio::test]
+async fn memory_transitions() {
+    let db = InMemoryStore::new();
+    let (user, category, condition) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+
+    let sold = Listing {
+        status: ListingStatus::Sold,
+        ..Default::default()
+    };
+    let res = db
+        .create_listing(&sold, &user, &category, &condition, 1)
+        .await;
+    assert!(res.is_err()); // listings start as drafts or active
+
+    let draft = Listing {
+        status: ListingStatus::Draft,
+        ..Default::default()
+    };
+    let draft = db
+        .create_listing(&draft, &user, &category, &condition, 1)
+        .await
+        .unwrap();
+
+    let res = db
+        .transition_listing(&draft.id, &user, ListingStatus::Sold)
+        .await;
+    assert!(res.is_err());
+
+    let active = db
+        .transition_listing(&draft.id, &user, ListingStatus::Active)
+        .await
+        .unwrap()
+        .expect("listing to exist");
+    assert_eq!(active.status, ListingStatus::Active);
+
+    // updates do not change the status
+    let update = Listing {
+        status: ListingStatus::Draft,
+        ..active.clone()
+    };
+    let updated = db
+        .update_listing(&draft.id, &update, &user, &category, &condition, 1)
+        .await
+        .unwrap()
+        .expect("listing to exist");
+    assert_eq!(updated.status, ListingStatus::Active);
+
+    let missing = db
+        .transition_listing(&Uuid::now_v7(), &user, ListingStatus::Active)
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}
+
+#[tokio::test]
+async fn memory_soft_delete() {
+    let db = InMemoryStore::new();
+    let (user, category, condition) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+
+    let listing = db
+        .create_listing(&Listing::default(), &user, &category, &condition, 1)
+        .await
+        .unwrap();
+
+    let deleted = db
+        .delete_listing(&listing.id, &user)
+        .await
+        .unwrap()
+        .expect("listing to exist");
+    assert!(deleted.deleted.is_some());
+    assert_eq!(db.get_listings().await.unwrap().len(), 0);
+    assert_eq!(db.get_listings_from_user(&user).await.unwrap().len(), 0);
+    assert!(db
+        .update_listing(&listing.id, &listing, &user, &category, &condition, 1)
+        .await
+        .unwrap()
+        .is_none());
+
+    let restored = db
+        .restore_listing(&listing.id, &user)
+        .await
+        .unwrap()
+        .expect("listing to be restored");
+    assert!(restored.deleted.is_none());
+    assert!(db
+        .restore_listing(&listing.id, &user)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(db.get_listings().await.unwrap().len(), 1);
+
+    db.delete_listing(&listing.id, &user).await.unwrap();
+    let purged = db
+        .purge_deleted_listings(Duration::from_secs(60 * 60))
+        .await
+        .unwrap();
+    assert_eq!(purged, 0); // still within the retention window
+
+    let purged = db.purge_deleted_listings(Duration::ZERO).await.unwrap();
+    assert_eq!(purged, 1);
+    assert!(matches!(
+        db.restore_listing(&listing.id, &user).await,
+        Err(CoreError::Database(_))
+    ));
+}
+
+#[tokio::test]
+async fn memory_enforces_ownership() {
+    let db = InMemoryStore::new();
+    let (owner, other, id) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+
+    let listing = db
+        .create_listing(&Listing::default(), &owner, &id, &id, 1)
+        .await
+        .unwrap();
+
+    let res = db
+        .update_listing(&listing.id, &listing, &other, &id, &id, 1)
+        .await;
+    assert!(matches!(res, Err(CoreError::Forbidden(_))));
+
+    let res = db
+        .transition_listing(&listing.id, &other, ListingStatus::Sold)
+        .await;
+    assert!(matches!(res, Err(CoreError::Forbidden(_))));
+
+    let res = db.delete_listing(&listing.id, &other).await;
+    assert!(matches!(res, Err(CoreError::Forbidden(_))));
+
+    db.delete_listing(&listing.id, &owner).await.unwrap();
+    let res = db.restore_listing(&listing.id, &other).await;
+    assert!(matches!(res, Err(CoreError::Forbidden(_))));
+}
+
+#[tokio::test]
+async fn memory_find_listings() {
+    let db = InMemoryStore::new();
+    let (user, phones, condition) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+
+    let listing = |title: &str, price: i64, negotiable: bool| Listing {
+        title: title.to_owned(),
+        price: Decimal::new(price, 0),
+        negotiable,
+        ..Default::default()
+    };
+
+    let cheap = db
+        .create_listing(
+            &listing("Cheap phone", 150, true),
+            &user,
+            &phones,
+            &condition,
+            1,
+        )
+        .await
+        .unwrap();
+    let firm = db
+        .create_listing(
+            &listing("Firm phone", 120, false),
+            &user,
+            &phones,
+            &condition,
+            1,
+        )
+        .await
+        .unwrap();
+    let laptop = db
+        .create_listing(
+            &listing("Laptop", 180, true),
+            &Uuid::now_v7(),
+            &Uuid::now_v7(),
+            &condition,
+            1,
+        )
+        .await
+        .unwrap();
+
+    let find = |filter: ListingFilter, sort: ListingSort| {
+        let db = db.clone();
+        async move {
+            db.find_listings(&filter, sort, PageRequest::first(10))
+                .await
+                .unwrap()
+                .items
+                .into_iter()
+                .map(|listing| listing.title)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let negotiable_phones = ListingFilter {
+        category_id: Some(phones),
+        max_price: Some(Decimal::new(200, 0)),
+        negotiable: Some(true),
+        ..Default::default()
+    };
+    assert_eq!(
+        find(negotiable_phones, ListingSort::CreatedDesc).await,
+        vec![cheap.title.clone()]
+    );
+
+    assert_eq!(
+        find(ListingFilter::default(), ListingSort::PriceAsc).await,
+        vec![
+            firm.title.clone(),
+            cheap.title.clone(),
+            laptop.title.clone()
+        ]
+    );
+
+    let by_user = ListingFilter {
+        user_id: Some(user),
+        ..Default::default()
+    };
+    assert_eq!(
+        find(by_user, ListingSort::PriceDesc).await,
+        vec![cheap.title.clone(), firm.title.clone()]
+    );
+
+    let drafts = ListingFilter {
+        status: Some(ListingStatus::Draft),
+        ..Default::default()
+    };
+    assert_eq!(find(drafts, ListingSort::CreatedAsc).await.len(), 0);
+
+    let tag = Uuid::now_v7();
+    db.tag_listing(&laptop.id, &[tag]).unwrap();
+    let tagged = ListingFilter {
+        tags: vec![tag],
+        ..Default::default()
+    };
+    assert_eq!(
+        find(tagged, ListingSort::CreatedAsc).await,
+        vec![laptop.title.clone()]
+    );
+
+    let sort = ListingSort::PriceAsc;
+    let page = |page: PageRequest| {
+        let db = db.clone();
+        async move {
+            db.find_listings(&ListingFilter::default(), sort, page)
+                .await
+                .unwrap()
+        }
+    };
+
+    let first = page(PageRequest::first(1)).await;
+    assert_eq!(first.total, 3);
+    assert_eq!(first.items, vec![firm.clone()]);
+
+    // a listing added before the cursor does not shift the next page
+    db.create_listing(&listing("Case", 10, true), &user, &phones, &condition, 1)
+        .await
+        .unwrap();
+    let next = page(PageRequest::first(1).with_after(Some(sort.key(&firm)))).await;
+    assert_eq!(next.total, 4);
+    assert_eq!(next.items, vec![cheap.clone()]);
+
+    let back = page(PageRequest::last(2).with_before(Some(sort.key(&laptop)))).await;
+    assert_eq!(back.items, vec![firm, cheap]);
+}
+
+#[tokio::test]
+async fn memory_webhooks() {
+    let db = InMemoryStore::new();
+
+    let webhook = db
+        .create_webhook(
+            "https://example.com/hooks",
+            "secret",
+            &[WebhookEvent::ListingCreated],
+        )
+        .await
+        .unwrap();
+    let webhooks: Vec<_> = db.get_webhooks().await.unwrap().collect();
+    assert_eq!(webhooks, vec![webhook.clone()]);
+
+    db.create_listing(
+        &Listing::default(),
+        &Uuid::now_v7(),
+        &Uuid::now_v7(),
+        &Uuid::now_v7(),
+        1,
+    )
+    .await
+    .unwrap();
+    assert_eq!(db.dispatch_webhooks(10).await.unwrap(), 0);
+    assert_eq!(
+        db.get_webhook_deliveries(&webhook.id, 10)
+            .await
+            .unwrap()
+            .len(),
+        0
+    );
+
+    assert_eq!(db.delete_webhook(&webhook.id).await.unwrap(), Some(webhook));
+    assert_eq!(db.get_webhooks().await.unwrap().len(), 0);
+}
@@ -1,10 +1,13 @@
 mod async_graphql;
 mod db;
+#[cfg(feature = "in-memory")]
+mod memory;
 
-use crate::{tests::db::SampleDbSend, Listing};
+use crate::{tests::db::SampleDbSend, Listing, ListingAction, ListingStatus, WebhookEvent};
 
 use self::db::SampleDb;
 use fake::{faker::lorem::en::Words, Fake};
+use rust_decimal::Decimal;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
@@ -20,16 +23,13 @@ impl Default for Listing {
             id: Uuid::now_v7(),
             title,
             description,
-            price: 250.50,
-            category_id: Uuid::now_v7(),
+            price: Decimal::new(25050, 2),
             image_url: String::from("https://dummyimage.com/420x260"),
             other_images: vec![],
-            published: true,
-            location_id: Uuid::now_v7(),
+            status: ListingStatus::Active,
             created: OffsetDateTime::now_utc(),
             deleted: None,
             updated: OffsetDateTime::now_utc(),
-            condition_id: Uuid::now_v7(),
             negotiable: true,
             expires: None,
         }
@@ -72,6 +72,63 @@ fn deserialise_list() {
     assert_eq!(source, listings);
 }
 
+#[test]
+fn status_transitions() {
+    assert!(ListingStatus::Draft.is_initial());
+    assert!(ListingStatus::Active.is_initial());
+    assert!(!ListingStatus::Sold.is_initial());
+
+    assert!(ListingStatus::Draft.can_transition_to(ListingStatus::Active));
+    assert!(ListingStatus::Active.can_transition_to(ListingStatus::Reserved));
+    assert!(ListingStatus::Reserved.can_transition_to(ListingStatus::Active));
+    assert!(ListingStatus::Reserved.can_transition_to(ListingStatus::Sold));
+    assert!(ListingStatus::Expired.can_transition_to(ListingStatus::Active));
+    assert!(ListingStatus::Sold.can_transition_to(ListingStatus::Removed));
+
+    assert!(!ListingStatus::Draft.can_transition_to(ListingStatus::Sold));
+    assert!(!ListingStatus::Active.can_transition_to(ListingStatus::Active));
+    assert!(!ListingStatus::Sold.can_transition_to(ListingStatus::Active));
+    assert!(!ListingStatus::Removed.can_transition_to(ListingStatus::Active));
+
+    let err = ListingStatus::Sold
+        .transition_to(ListingStatus::Draft)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "listing cannot move from sold to draft");
+}
+
+#[test]
+fn webhook_events() {
+    use ListingStatus::*;
+
+    let event = |action, previous| WebhookEvent::from_change(action, previous, Active);
+
+    assert_eq!(
+        event(ListingAction::Created, None),
+        WebhookEvent::ListingCreated
+    );
+    assert_eq!(
+        event(ListingAction::Updated, None),
+        WebhookEvent::ListingUpdated
+    );
+    assert_eq!(
+        event(ListingAction::Updated, Some(Active)),
+        WebhookEvent::ListingUpdated
+    );
+    assert_eq!(
+        event(ListingAction::Updated, Some(Draft)),
+        WebhookEvent::ListingStatusChanged
+    );
+    assert_eq!(
+        event(ListingAction::Deleted, Some(Draft)),
+        WebhookEvent::ListingDeleted
+    );
+
+    assert_eq!(
+        serde_json::to_string(&WebhookEvent::ListingStatusChanged).unwrap(),
+        format!("\"{}\"", WebhookEvent::ListingStatusChanged)
+    );
+}
+
 #[tokio::test]
 async fn trait_blank_queries() {
     use crate::api::LocalQueryListings;
@@ -100,11 +157,13 @@ async fn trait_blank_mutations() {
     let listing = Listing::default();
 
     let user = Uuid::now_v7();
-    let db = SampleDb.create_listing(&listing, &user).await;
+    let id = Uuid::now_v7();
+    let db = SampleDb.create_listing(&listing, &user, &id, &id, 1).await;
     assert!(db.is_ok());
 
-    let id = Uuid::now_v7();
-    let db = SampleDb.update_listing(&id, &listing, &user).await;
+    let db = SampleDb
+        .update_listing(&id, &listing, &user, &id, &id, 1)
+        .await;
     assert!(db.is_ok());
 
     let db = SampleDb.delete_listing(&id, &user).await;
@@ -119,10 +178,14 @@ async fn mutation_returns_send() {
 
     let user = Uuid::now_v7();
     let id = Uuid::now_v7();
-    let db = SampleDbSend.create_listing(&listing, &user).await;
+    let db = SampleDbSend
+        .create_listing(&listing, &user, &id, &id, 1)
+        .await;
     assert!(db.is_ok());
 
-    let db = SampleDbSend.update_listing(&id, &listing, &user).await;
+    let db = SampleDbSend
+        .update_listing(&id, &listing, &user, &id, &id, 1)
+        .await;
     assert!(db.is_ok());
 
     let db = SampleDbSend.delete_listing(&id, &user).await;
@@ -0,0 +1,135 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+#[cfg(feature = "async-graphql")]
+use async_graphql::{Enum, SimpleObject};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{ListingAction, ListingStatus};
+
+/// The listing lifecycle events a webhook can be told about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(Enum))]
+pub enum WebhookEvent {
+    #[cfg_attr(feature = "serde", serde(rename = "listing.created"))]
+    ListingCreated,
+    #[cfg_attr(feature = "serde", serde(rename = "listing.updated"))]
+    ListingUpdated,
+    #[cfg_attr(feature = "serde", serde(rename = "listing.deleted"))]
+    ListingDeleted,
+    #[cfg_attr(feature = "serde", serde(rename = "listing.status_changed"))]
+    ListingStatusChanged,
+}
+
+impl WebhookEvent {
+    /// The event a change is reported as. Updates that move a listing from `previous` to
+    /// another status are reported as [`WebhookEvent::ListingStatusChanged`]
+    pub fn from_change(
+        action: ListingAction,
+        previous: Option<ListingStatus>,
+        current: ListingStatus,
+    ) -> Self {
+        match action {
+            ListingAction::Created => Self::ListingCreated,
+            ListingAction::Deleted => Self::ListingDeleted,
+            ListingAction::Updated if previous.is_some_and(|previous| previous != current) => {
+                Self::ListingStatusChanged
+            }
+            ListingAction::Updated => Self::ListingUpdated,
+        }
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event = match self {
+            Self::ListingCreated => "listing.created",
+            Self::ListingUpdated => "listing.updated",
+            Self::ListingDeleted => "listing.deleted",
+            Self::ListingStatusChanged => "listing.status_changed",
+        };
+        f.write_str(event)
+    }
+}
+
+/// Whether an address only reaches this host or its private network, so webhooks must never be
+/// posted to it
+pub fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_ipv4(ip),
+        IpAddr::V6(ip) => is_internal_ipv6(ip),
+    }
+}
+
+fn is_internal_ipv4(ip: Ipv4Addr) -> bool {
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+}
+
+fn is_internal_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // unique local and link local addresses
+        || first & 0xfe00 == 0xfc00
+        || first & 0xffc0 == 0xfe80
+        || ip.to_ipv4_mapped().is_some_and(is_internal_ipv4)
+}
+
+/// An endpoint the listing events it subscribed to are posted to
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct Webhook {
+    pub id: Uuid,
+    pub url: String,
+    /// Signs every payload posted to the endpoint. It is stored in plaintext, as it is needed
+    /// to sign, and never handed back out
+    #[cfg_attr(feature = "async-graphql", graphql(skip))]
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub created: OffsetDateTime,
+}
+
+/// Where a delivery is at. Pending deliveries are retried until they succeed or run out of
+/// attempts, at which point they fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[cfg_attr(feature = "async-graphql", derive(Enum))]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+/// An event posted, or still to be posted, to a webhook
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: WebhookEvent,
+    /// Shared by the deliveries of the same change to every webhook, so receivers can tell
+    /// retries apart from new events
+    pub event_id: Uuid,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// The status code of the last response, if the endpoint answered
+    pub response_status: Option<u16>,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    pub created: OffsetDateTime,
+    pub delivered: Option<OffsetDateTime>,
+}
@@ -1,3 +1,4 @@
+use api_core::api::CoreError;
 use serde::{Deserialize, Serialize};
 use surrealdb::{
     opt::{IntoResource, Resource},
@@ -13,13 +14,27 @@ pub(crate) enum Collection {
     Tag,
     ListingCondition,
     Category,
+    Outbox,
+    Webhook,
+    WebhookDelivery,
+    Migration,
 }
 
-impl From<&str> for Collection {
-    fn from(value: &str) -> Self {
+impl TryFrom<&str> for Collection {
+    type Error = CoreError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
-            "user" => Self::User,
-            _ => unimplemented!("{value}"),
+            "listing" => Ok(Self::Listing),
+            "user" => Ok(Self::User),
+            "tag" => Ok(Self::Tag),
+            "listing_condition" => Ok(Self::ListingCondition),
+            "category" => Ok(Self::Category),
+            "outbox" => Ok(Self::Outbox),
+            "webhook" => Ok(Self::Webhook),
+            "webhook_delivery" => Ok(Self::WebhookDelivery),
+            "migration" => Ok(Self::Migration),
+            _ => Err(CoreError::Database(format!("unknown collection: {value}"))),
         }
     }
 }
@@ -35,6 +50,10 @@ impl std::fmt::Display for Collection {
                 Collection::User => "user",
                 Collection::ListingCondition => "listing_condition",
                 Collection::Category => "category",
+                Collection::Outbox => "outbox",
+                Collection::Webhook => "webhook",
+                Collection::WebhookDelivery => "webhook_delivery",
+                Collection::Migration => "migration",
             }
         )
     }
@@ -23,3 +23,21 @@ impl TryFrom<DatabaseEntityListingCondition> for ListingCondition {
         })
     }
 }
+
+/// A listing along with the condition its `withCondition` edge points to
+#[derive(Deserialize, Debug)]
+pub(crate) struct DatabaseEntityListingWithCondition {
+    pub id: RecordId,
+    pub condition: Option<DatabaseEntityListingCondition>,
+}
+
+impl DatabaseEntityListingWithCondition {
+    pub(crate) fn into_condition(self) -> Result<Option<(Uuid, ListingCondition)>, CoreError> {
+        let Some(condition) = self.condition else {
+            return Ok(None);
+        };
+        let listing_id = Uuid::parse_str(&create_string_from_id(&self.id))?;
+
+        Ok(Some((listing_id, ListingCondition::try_from(condition)?)))
+    }
+}
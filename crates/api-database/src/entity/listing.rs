@@ -1,6 +1,6 @@
 use std::fmt;
 
-use api_core::{api::CoreError, reexports::uuid::Uuid, Listing};
+use api_core::{api::CoreError, reexports::uuid::Uuid, Listing, ListingEdges, ListingStatus};
 use rust_decimal::Decimal;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use surrealdb::opt::RecordId;
@@ -8,7 +8,7 @@ use time::{format_description::well_known::Iso8601, OffsetDateTime};
 
 use super::create_string_from_id;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct DatabaseEntityListing {
     pub id: RecordId,
     pub title: String,
@@ -16,7 +16,7 @@ pub(crate) struct DatabaseEntityListing {
     pub price: Decimal,
     pub image_url: String,
     pub other_images: Vec<String>,
-    pub active: bool,
+    pub status: ListingStatus,
     pub negotiable: bool,
     #[serde(deserialize_with = "date_time_opt")]
     pub expires: Option<OffsetDateTime>,
@@ -49,7 +49,7 @@ where
     )
 }
 
-fn deserialize_date_time<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+pub(crate) fn deserialize_date_time<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
 where
     D: de::Deserializer<'de>,
 {
@@ -86,7 +86,7 @@ impl TryFrom<DatabaseEntityListing> for Listing {
             description: entity.description,
             price: entity.price,
             other_images: entity.other_images,
-            published: entity.active,
+            status: entity.status,
             negotiable: entity.negotiable,
             created: entity.created,
             deleted: entity.deleted,
@@ -96,3 +96,36 @@ impl TryFrom<DatabaseEntityListing> for Listing {
         })
     }
 }
+
+/// The records a listing's edges point to, along with the quantity on its `sells` edge
+#[derive(Deserialize, Debug)]
+pub(crate) struct DatabaseEntityListingEdges {
+    pub id: RecordId,
+    pub user: Option<RecordId>,
+    pub category: Option<RecordId>,
+    pub condition: Option<RecordId>,
+    pub quantity: Option<usize>,
+}
+
+impl DatabaseEntityListingEdges {
+    /// Listings missing any edge were never fully created
+    pub(crate) fn into_edges(self) -> Result<Option<(Uuid, ListingEdges)>, CoreError> {
+        let (Some(user), Some(category), Some(condition), Some(quantity)) =
+            (self.user, self.category, self.condition, self.quantity)
+        else {
+            return Ok(None);
+        };
+
+        let listing_id = Uuid::parse_str(&create_string_from_id(&self.id))?;
+
+        Ok(Some((
+            listing_id,
+            ListingEdges {
+                user_id: Uuid::parse_str(&create_string_from_id(&user))?,
+                category_id: Uuid::parse_str(&create_string_from_id(&category))?,
+                condition_id: Uuid::parse_str(&create_string_from_id(&condition))?,
+                quantity,
+            },
+        )))
+    }
+}
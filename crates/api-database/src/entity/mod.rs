@@ -5,6 +5,8 @@ use crate::collections::Collection;
 
 pub(crate) mod condition;
 pub(crate) mod listing;
+pub(crate) mod outbox;
+pub(crate) mod webhook;
 
 pub(crate) fn create_thing_from_id(collection: Collection, id: &Uuid) -> RecordId {
     RecordId::from((collection.to_string(), id.to_string()))
@@ -0,0 +1,54 @@
+use api_core::{api::CoreError, reexports::uuid::Uuid, ListingAction, ListingStatus, WebhookEvent};
+use serde::Deserialize;
+use surrealdb::opt::RecordId;
+
+use super::{create_string_from_id, listing::DatabaseEntityListing};
+
+/// A listing change, recorded in the same transaction as the write that made it
+#[derive(Deserialize, Debug)]
+pub(crate) struct DatabaseEntityOutbox {
+    pub id: RecordId,
+    pub action: ListingAction,
+    /// The listing as it was written
+    pub listing: DatabaseEntityListing,
+    pub seller: Option<RecordId>,
+    pub category: Option<RecordId>,
+    pub condition: Option<RecordId>,
+    /// The status the listing moved from, when the change moved it
+    pub previous_status: Option<ListingStatus>,
+    /// How many times delivery has been started
+    pub attempts: u32,
+}
+
+impl DatabaseEntityOutbox {
+    /// Identifies the change to everyone it is delivered to
+    pub(crate) fn event_id(&self) -> Result<Uuid, CoreError> {
+        Ok(Uuid::parse_str(&create_string_from_id(&self.id))?)
+    }
+
+    pub(crate) fn listing_id(&self) -> Result<Uuid, CoreError> {
+        Ok(Uuid::parse_str(&create_string_from_id(&self.listing.id))?)
+    }
+
+    pub(crate) fn seller_id(&self) -> Result<Option<Uuid>, CoreError> {
+        parse_id(self.seller.as_ref())
+    }
+
+    pub(crate) fn category_id(&self) -> Result<Option<Uuid>, CoreError> {
+        parse_id(self.category.as_ref())
+    }
+
+    pub(crate) fn condition_id(&self) -> Result<Option<Uuid>, CoreError> {
+        parse_id(self.condition.as_ref())
+    }
+
+    pub(crate) fn webhook_event(&self) -> WebhookEvent {
+        WebhookEvent::from_change(self.action, self.previous_status, self.listing.status)
+    }
+}
+
+fn parse_id(id: Option<&RecordId>) -> Result<Option<Uuid>, CoreError> {
+    id.map(|id| Uuid::parse_str(&create_string_from_id(id)))
+        .transpose()
+        .map_err(CoreError::from)
+}
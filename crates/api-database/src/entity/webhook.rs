@@ -0,0 +1,72 @@
+use api_core::{
+    api::CoreError, reexports::uuid::Uuid, DeliveryStatus, Webhook, WebhookDelivery, WebhookEvent,
+};
+use serde::Deserialize;
+use surrealdb::opt::RecordId;
+use time::OffsetDateTime;
+
+use super::{
+    create_string_from_id,
+    listing::{date_time_opt, deserialize_date_time},
+};
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct DatabaseEntityWebhook {
+    pub id: RecordId,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    #[serde(deserialize_with = "deserialize_date_time")]
+    pub created: OffsetDateTime,
+}
+
+impl TryFrom<DatabaseEntityWebhook> for Webhook {
+    type Error = CoreError;
+
+    fn try_from(entity: DatabaseEntityWebhook) -> Result<Self, Self::Error> {
+        Ok(Webhook {
+            id: Uuid::parse_str(&create_string_from_id(&entity.id))?,
+            url: entity.url,
+            secret: entity.secret,
+            events: entity.events,
+            created: entity.created,
+        })
+    }
+}
+
+/// A delivery along with the payload it posts, which is signed and sent as is on every attempt
+#[derive(Deserialize, Debug)]
+pub(crate) struct DatabaseEntityWebhookDelivery {
+    pub id: RecordId,
+    pub webhook: RecordId,
+    pub event: WebhookEvent,
+    pub event_id: String,
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    #[serde(deserialize_with = "deserialize_date_time")]
+    pub created: OffsetDateTime,
+    #[serde(deserialize_with = "date_time_opt")]
+    pub delivered: Option<OffsetDateTime>,
+}
+
+impl TryFrom<DatabaseEntityWebhookDelivery> for WebhookDelivery {
+    type Error = CoreError;
+
+    fn try_from(entity: DatabaseEntityWebhookDelivery) -> Result<Self, Self::Error> {
+        Ok(WebhookDelivery {
+            id: Uuid::parse_str(&create_string_from_id(&entity.id))?,
+            webhook_id: Uuid::parse_str(&create_string_from_id(&entity.webhook))?,
+            event: entity.event,
+            event_id: Uuid::parse_str(&entity.event_id)?,
+            status: entity.status,
+            attempts: entity.attempts,
+            response_status: entity.response_status,
+            error: entity.error,
+            created: entity.created,
+            delivered: entity.delivered,
+        })
+    }
+}
@@ -86,6 +86,17 @@ pub(crate) async fn find_user_by_id(
     users_api: &str,
     variables: user_by_id::Variables,
 ) -> Result<bool, CoreError> {
+    Ok(get_user_by_id(client, users_api, variables)
+        .await?
+        .is_some())
+}
+
+#[tracing::instrument(skip(variables))]
+pub(crate) async fn get_user_by_id(
+    client: &reqwest::Client,
+    users_api: &str,
+    variables: user_by_id::Variables,
+) -> Result<Option<user_by_id::UserByIdUserById>, CoreError> {
     let context = Span::current().context();
     let request_body = userById::build_query(variables);
 
@@ -105,15 +116,9 @@ pub(crate) async fn find_user_by_id(
 
     let response_body: Response<user_by_id::ResponseData> = resp.json().await.map_err(map_err)?;
 
-    let located = if let Some(resp) = response_body.data {
-        if let Some(val) = resp.user_by_id {
-            trace!("found user: {}", val.id);
-            true
-        } else {
-            false
-        }
-    } else {
-        false
-    };
-    Ok(located)
+    let user = response_body.data.and_then(|resp| resp.user_by_id);
+    if let Some(ref val) = user {
+        trace!("found user: {}", val.id);
+    }
+    Ok(user)
 }
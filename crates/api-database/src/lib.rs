@@ -1,6 +1,6 @@
-use std::sync::Arc;
+use std::{num::NonZeroUsize, sync::Arc};
 
-use api_core::api::CoreError;
+use api_core::{api::CoreError, reexports::uuid::Uuid};
 use s3::Bucket;
 use thiserror::Error;
 
@@ -9,9 +9,12 @@ pub(crate) mod entity;
 mod file_storage;
 mod graphql_requests;
 mod mutation;
+mod outbox;
 mod query;
 mod redis;
+mod webhook;
 pub use file_storage::S3Config;
+pub use graphql_requests::user_by_id::UserType;
 
 use surrealdb::{
     engine::remote::ws::{Client as SurrealClient, Ws},
@@ -20,23 +23,39 @@ use surrealdb::{
 };
 use tracing::{instrument, trace};
 
-use self::redis::RedisPool;
+use self::collections::Collection;
+use self::redis::{
+    local_cache::{self, LocalCache},
+    pubsub::ChangeListener,
+    single_flight::Flights,
+    RedisPool,
+};
 
 pub(crate) fn map_db_error(error: surrealdb::Error) -> CoreError {
     CoreError::Database(error.to_string())
 }
 
+#[derive(Clone)]
 pub struct Client {
     client: Surreal<SurrealClient>,
     redis: Option<(RedisPool, u64)>,
+    /// Cache loads running in this process, shared by concurrent misses of the same key
+    flights: Flights,
+    /// Entries read recently, kept in this process ahead of redis
+    local_cache: Option<LocalCache>,
+    /// Where changes made by every replica are read from, once redis is set up
+    changes: Option<ChangeListener>,
     search_client: Option<meilisearch_sdk::Client>,
     http_client: reqwest::Client,
+    /// Posts webhook deliveries, which must not reach anything but public hosts
+    webhook_client: reqwest::Client,
     users_api: Arc<str>,
     categories_api: Arc<str>,
     storage_bucket: Bucket,
 }
 
 impl Client {
+    /// Caches reads in redis and shares listing changes with every other replica through it
     #[instrument(skip_all)]
     pub async fn with_redis(&mut self, dsn: &str, is_cluster: bool, pool_size: u16, ttl: u64) {
         trace!("connecting to redis");
@@ -47,7 +66,24 @@ impl Client {
                 redis::new_redis_pool(dsn, pool_size).await
             },
             ttl,
-        ))
+        ));
+        self.changes =
+            Some(ChangeListener::new(dsn).expect("Error initializing redis pub/sub client"));
+    }
+
+    /// Keeps up to `capacity` of the entries read from redis in this process for at most `ttl`
+    /// milliseconds. Entries any replica clears from redis are dropped here too, as the keys it
+    /// clears are published through redis, so it is set up after [`Client::with_redis`]
+    #[instrument(skip(self))]
+    pub fn with_local_cache(&mut self, capacity: NonZeroUsize, ttl: u64) {
+        let cache = LocalCache::new(capacity, ttl);
+        if let Some(ref changes) = self.changes {
+            tokio::spawn(local_cache::evict_invalidated(
+                changes.clone(),
+                cache.clone(),
+            ));
+        }
+        self.local_cache = Some(cache);
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -70,27 +106,85 @@ impl Client {
         db.signin(Root { username, password }).await?;
 
         db.use_ns(namespace).use_db(database).await?;
+        migrate_listing_status(&db).await?;
 
         let http_client = reqwest::Client::new();
+        let webhook_client = webhook::client::http_client()?;
 
         Ok(Client {
             client: db,
             search_client: None,
             redis: None,
+            flights: Flights::default(),
+            local_cache: None,
+            changes: None,
             http_client,
+            webhook_client,
             users_api: users_api.into(),
             categories_api: categories_api.into(),
             storage_bucket: bucket,
         })
     }
 
+    /// Searches listings with meilisearch. The attributes searches filter on are set up front,
+    /// so they are in place however the listings index comes to be created
     #[instrument(skip_all)]
-    pub fn with_meilisearch(&mut self, host: &str, api_key: Option<impl Into<String>>) {
+    pub async fn with_meilisearch(
+        &mut self,
+        host: &str,
+        api_key: Option<impl Into<String>>,
+    ) -> Result<(), ClientError> {
         trace!("connecting to meilisearch");
-        self.search_client = Some(meilisearch_sdk::Client::new(host, api_key));
+        let client = meilisearch_sdk::Client::new(host, api_key);
+        client
+            .index(query::LISTINGS_INDEX)
+            .set_filterable_attributes(["status", "deleted"])
+            .await?;
+
+        self.search_client = Some(client);
+        Ok(())
+    }
+
+    /// Looks up the account type of a user in the users service
+    #[instrument(skip(self), err(Debug))]
+    pub async fn user_type(&self, user_id: &Uuid) -> Result<Option<UserType>, CoreError> {
+        let user = graphql_requests::get_user_by_id(
+            &self.http_client,
+            &self.users_api,
+            graphql_requests::user_by_id::Variables { id: *user_id },
+        )
+        .await?;
+
+        Ok(user.map(|user| user.user_type))
     }
 }
 
+/// Listings stored before they had a lifecycle status only carry the `active` flag, which
+/// becomes the `active` or `draft` status. It is recorded once done, so later boots only look
+/// up that record rather than scan the listings again
+#[instrument(skip_all, err(Debug))]
+async fn migrate_listing_status(db: &Surreal<SurrealClient>) -> Result<(), surrealdb::Error> {
+    db.query(
+        "BEGIN TRANSACTION;
+        LET $applied = (SELECT VALUE applied FROM type::thing($migration_tbl, $name))[0];
+        IF !$applied {
+            UPDATE type::table($table) SET
+                status = IF active THEN 'active' ELSE 'draft' END,
+                active = NONE
+            WHERE status = NONE;
+            UPDATE type::thing($migration_tbl, $name) SET applied = time::now();
+        };
+        COMMIT TRANSACTION;",
+    )
+    .bind(("table", Collection::Listing))
+    .bind(("migration_tbl", Collection::Migration))
+    .bind(("name", "listing_status"))
+    .await?
+    .check()?;
+
+    Ok(())
+}
+
 #[derive(Error, Debug)]
 pub enum ClientError {
     #[error("database engine error")]
@@ -99,6 +193,10 @@ pub enum ClientError {
     Redaction(String),
     #[error(transparent)]
     Bucket(#[from] s3::error::S3Error),
+    #[error("http client error")]
+    Http(#[from] reqwest::Error),
+    #[error("search engine error")]
+    Search(#[from] meilisearch_sdk::errors::Error),
     #[error("invalid header (expected {expected:?}, found {found:?})")]
     InvalidHeader { expected: String, found: String },
     #[error("unknown data store error")]
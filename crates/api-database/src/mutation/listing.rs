@@ -2,15 +2,17 @@ use crate::{
     collections::Collection,
     entity::listing::DatabaseEntityListing,
     graphql_requests::{find_category_by_id, find_user_by_id},
-    redis::{cache_keys::CacheKey, PoolLike, PooledConnectionLike, RedisPool},
+    outbox::{self, record_change},
+    redis::pubsub,
 };
 use api_core::{
-    api::{CoreError, MutateListings},
+    api::{CoreError, MutateListings, QueryListings},
     reexports::uuid::Uuid,
-    Listing,
+    Listing, ListingAction, ListingEvent, ListingStatus,
 };
 use futures_util::TryFutureExt;
 use rust_decimal::Decimal;
+use std::time::Duration;
 use time::OffsetDateTime;
 use tracing::{debug, error, instrument, trace};
 
@@ -55,20 +57,22 @@ async fn check_listing_validity(
     Ok(())
 }
 
-async fn clear_listing_cache(redis: &RedisPool, user_id: &Uuid) {
-    match redis.get().await {
-        Ok(mut pool) => {
-            let mut pipe = redis::Pipeline::new();
-            pipe.del(CacheKey::AllListings)
-                .del(CacheKey::UserListing { user_id });
+async fn check_listing_owner(client: &Client, id: &Uuid, user_id: &Uuid) -> Result<(), CoreError> {
+    match client.get_listing_owner(id).await? {
+        Some(owner) if owner == *user_id => Ok(()),
+        Some(_) => Err(CoreError::Forbidden(format!(
+            "user: {} does not sell listing: {}",
+            user_id, id
+        ))),
+        // every listing is created with a seller, so one without any does not exist
+        None => Err(CoreError::Database(format!("listing: {id} does not exist"))),
+    }
+}
 
-            if let Err(e) = pool.query_async_pipeline::<()>(pipe).await {
-                error!("{e}");
-            }
-        }
-        Err(e) => {
-            error!("{e}");
-        }
+/// Shares a change with every replica, whose subscribers hear about it through redis
+async fn publish_change(client: &Client, action: ListingAction, listing: &Listing) {
+    if let Some((ref redis, _ttl)) = client.redis {
+        pubsub::publish(redis, &ListingEvent::new(action, listing.clone())).await;
     }
 }
 
@@ -82,15 +86,23 @@ impl MutateListings for Client {
         condition_id: &Uuid,
         quantity: usize,
     ) -> Result<Listing, CoreError> {
+        if !listing.status.is_initial() {
+            return Err(CoreError::InvalidTransition {
+                from: ListingStatus::Draft,
+                to: listing.status,
+            });
+        }
+
         check_listing_validity(self, category_id, user_id).await?;
 
         let input = InputListing::from(listing);
+        let event_id = Uuid::now_v7();
         trace!("creating listing");
 
         // TODO: fix location
         let mut item = self
             .client
-            .query(
+            .query(concat!(
                 "BEGIN TRANSACTION;
             LET $listing = (CREATE ONLY listing:uuid() CONTENT {
                 title: type::string($title),
@@ -98,7 +110,7 @@ impl MutateListings for Client {
                 image_url: type::string($img_url),
                 price: type::decimal($price),
                 other_images: [],
-                active: type::bool($active),
+                status: type::string($status),
                 negotiable: type::bool($negotiable),
                 updated: time::now(),
                 deleted: NULL,
@@ -124,10 +136,11 @@ impl MutateListings for Client {
             RELATE $listing_id->withCondition->$condition_id CONTENT {
                  in: $listing_id,
                  out: $condition_id
-            };
-            RETURN $listing;
+            };",
+                record_change!(),
+                "RETURN $listing;
             COMMIT TRANSACTION;",
-            )
+            ))
             .bind(("title", input.title))
             .bind(("description", input.description))
             .bind(("img_url", input.image_url))
@@ -135,15 +148,18 @@ impl MutateListings for Client {
             .bind(("category_tbl", Collection::Category))
             .bind(("category_id", category_id.to_string()))
             // .bind(("other_img", input.title))
-            .bind(("active", input.active))
+            .bind(("status", listing.status))
             .bind(("negotiable", input.negotiable))
             .bind(("condition_id", condition_id.to_string()))
             .bind(("condition_tbl", Collection::ListingCondition))
-            .bind(("expires", input.expires_at))
+            .bind(("expires", input.expires))
             .bind(("user_id", user_id.to_string()))
             .bind(("region_tbl", "region"))
             .bind(("quantity", quantity))
             .bind(("user_tbl", Collection::User))
+            .bind(("outbox_tbl", Collection::Outbox))
+            .bind(("event_id", event_id.to_string()))
+            .bind(("action", ListingAction::Created))
             .await
             .map_err(map_db_error)?;
 
@@ -152,9 +168,8 @@ impl MutateListings for Client {
         match resp {
             Some(e) => {
                 let listing = Listing::try_from(e)?;
-                if let Some((ref redis, _ttl)) = self.redis {
-                    clear_listing_cache(redis, user_id).await;
-                };
+                outbox::deliver_now(self, &event_id).await;
+                publish_change(self, ListingAction::Created, &listing).await;
                 trace!("listing created");
                 debug!("listing content: {:?}", listing);
                 Ok(listing)
@@ -174,26 +189,68 @@ impl MutateListings for Client {
         quantity: usize,
     ) -> Result<Option<Listing>, CoreError> {
         check_listing_validity(self, category_id, user_id).await?;
+        check_listing_owner(self, id, user_id).await?;
 
         let input = InputListing::from(data);
+        let event_id = Uuid::now_v7();
 
-        let item: Option<DatabaseEntityListing> = self
+        // the quantity lives on the `sells` edge, which nothing watches. It is written along with
+        // the listing itself, so live queries on the listing still report a change to it alone
+        let mut item = self
             .client
-            .update((Collection::Listing.to_string(), id.to_string()))
-            .merge(input)
+            .query(concat!(
+                "BEGIN TRANSACTION;
+                LET $listing = (UPDATE type::thing($table, $id) SET
+                    title = type::string($title),
+                    description = type::string($description),
+                    image_url = type::string($img_url),
+                    price = type::decimal($price),
+                    other_images = $other_images,
+                    negotiable = type::bool($negotiable),
+                    updated = time::now(),
+                    expires = IF type::is::none($expires) OR type::is::null($expires) THEN
+                                NULL
+                              ELSE
+                                type::datetime($expires)
+                              END
+                    WHERE !deleted
+                    RETURN AFTER)[0];
+                IF $listing {
+                    UPDATE sells SET quantity = type::int($quantity) WHERE out = $listing.id;
+                };",
+                record_change!(),
+                "RETURN $listing;
+                COMMIT TRANSACTION;",
+            ))
+            .bind(("table", Collection::Listing))
+            .bind(("id", id.to_string()))
+            .bind(("title", input.title))
+            .bind(("description", input.description))
+            .bind(("img_url", input.image_url))
+            .bind(("price", input.price))
+            .bind(("other_images", input.other_images))
+            .bind(("negotiable", input.negotiable))
+            .bind(("expires", input.expires))
+            .bind(("quantity", quantity))
+            .bind(("user_tbl", Collection::User))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("outbox_tbl", Collection::Outbox))
+            .bind(("event_id", event_id.to_string()))
+            .bind(("action", ListingAction::Updated))
             .await
             .map_err(map_db_error)?;
 
+        let item: Option<DatabaseEntityListing> = item.take(0).map_err(map_db_error)?;
+
         match item {
             Some(e) => {
                 let listing = Listing::try_from(e)?;
-                if let Some((ref redis, _ttl)) = self.redis {
-                    clear_listing_cache(redis, user_id).await;
-                };
+                outbox::deliver_now(self, &event_id).await;
+                publish_change(self, ListingAction::Updated, &listing).await;
                 debug!("listing updated");
                 Ok(Some(listing))
             }
-            None => Err(CoreError::Unreachable),
+            None => Ok(None),
         }
     }
 
@@ -203,17 +260,38 @@ impl MutateListings for Client {
         id: &Uuid,
         user_id: &Uuid,
     ) -> Result<Option<Listing>, CoreError> {
-        let listing: Option<DatabaseEntityListing> = self
+        check_listing_owner(self, id, user_id).await?;
+        let event_id = Uuid::now_v7();
+
+        let mut item = self
             .client
-            .delete((Collection::Listing.to_string(), id.to_string()))
+            .query(concat!(
+                "BEGIN TRANSACTION;
+                LET $listing = (UPDATE type::thing($table, $id) SET
+                    deleted = time::now(),
+                    updated = time::now()
+                WHERE !deleted
+                RETURN AFTER)[0];",
+                record_change!(),
+                "RETURN $listing;
+                COMMIT TRANSACTION;",
+            ))
+            .bind(("table", Collection::Listing))
+            .bind(("id", id.to_string()))
+            .bind(("user_tbl", Collection::User))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("outbox_tbl", Collection::Outbox))
+            .bind(("event_id", event_id.to_string()))
+            .bind(("action", ListingAction::Deleted))
             .await
             .map_err(map_db_error)?;
 
-        match listing.map(Listing::try_from) {
+        let item: Option<DatabaseEntityListing> = item.take(0).map_err(map_db_error)?;
+
+        match item.map(Listing::try_from) {
             Some(Ok(listing)) => {
-                if let Some((ref redis, _ttl)) = self.redis {
-                    clear_listing_cache(redis, user_id).await;
-                };
+                outbox::deliver_now(self, &event_id).await;
+                publish_change(self, ListingAction::Deleted, &listing).await;
                 Ok(Some(listing))
             }
             Some(Err(e)) => {
@@ -226,6 +304,207 @@ impl MutateListings for Client {
         }
     }
 
+    #[instrument(skip(self), err(Debug))]
+    async fn restore_listing(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<Option<Listing>, CoreError> {
+        check_listing_owner(self, id, user_id).await?;
+        let event_id = Uuid::now_v7();
+
+        let mut item = self
+            .client
+            .query(concat!(
+                "BEGIN TRANSACTION;
+                LET $listing = (UPDATE type::thing($table, $id) SET
+                    deleted = NULL,
+                    updated = time::now()
+                WHERE deleted
+                RETURN AFTER)[0];",
+                record_change!(),
+                "RETURN $listing;
+                COMMIT TRANSACTION;",
+            ))
+            .bind(("table", Collection::Listing))
+            .bind(("id", id.to_string()))
+            .bind(("user_tbl", Collection::User))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("outbox_tbl", Collection::Outbox))
+            .bind(("event_id", event_id.to_string()))
+            .bind(("action", ListingAction::Updated))
+            .await
+            .map_err(map_db_error)?;
+
+        let item: Option<DatabaseEntityListing> = item.take(0).map_err(map_db_error)?;
+
+        match item {
+            Some(e) => {
+                let listing = Listing::try_from(e)?;
+                outbox::deliver_now(self, &event_id).await;
+                publish_change(self, ListingAction::Updated, &listing).await;
+                debug!("listing restored");
+                Ok(Some(listing))
+            }
+            None => Ok(None),
+        }
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn purge_deleted_listings(&self, retention: Duration) -> Result<usize, CoreError> {
+        let mut expired = self
+            .client
+            .query(
+                "SELECT * FROM type::table($table)
+                WHERE deleted AND deleted <= time::now() - type::duration($retention)",
+            )
+            .bind(("table", Collection::Listing))
+            .bind(("retention", format!("{}s", retention.as_secs())))
+            .await
+            .map_err(map_db_error)?;
+
+        let expired: Vec<DatabaseEntityListing> = expired.take(0).map_err(map_db_error)?;
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        // images go first so a failed purge never leaves objects without a listing
+        let bucket = &self.storage_bucket;
+        let images = expired
+            .iter()
+            .flat_map(|listing| std::iter::once(&listing.image_url).chain(&listing.other_images))
+            .map(|image| async move {
+                if let Err(e) = bucket.delete_object(image_key(image)).await {
+                    error!(image = %image, "{e}");
+                }
+            });
+        futures_util::future::join_all(images).await;
+
+        let ids: Vec<_> = expired.iter().map(|listing| listing.id.clone()).collect();
+
+        self.client
+            .query(
+                "BEGIN TRANSACTION;
+                FOR $listing IN (
+                    SELECT *,
+                        (<-sells<-user)[0] AS seller,
+                        (->inCategory->category)[0] AS category,
+                        (->withCondition->listing_condition)[0] AS condition
+                    FROM type::table($table)
+                    WHERE id INSIDE $ids
+                ) {
+                    CREATE type::thing($outbox_tbl, <string> rand::uuid::v7()) CONTENT {
+                        action: $action,
+                        listing: $listing,
+                        seller: $listing.seller,
+                        category: $listing.category,
+                        condition: $listing.condition,
+                        attempts: 0,
+                        created: time::now(),
+                        available: time::now()
+                    };
+                };
+                DELETE sells WHERE out INSIDE $ids;
+                DELETE inCategory WHERE in INSIDE $ids;
+                DELETE withCondition WHERE in INSIDE $ids;
+                DELETE type::table($table) WHERE id INSIDE $ids;
+                COMMIT TRANSACTION;",
+            )
+            .bind(("table", Collection::Listing))
+            .bind(("ids", &ids))
+            .bind(("outbox_tbl", Collection::Outbox))
+            .bind(("action", ListingAction::Deleted))
+            .await
+            .map_err(map_db_error)?
+            .check()
+            .map_err(map_db_error)?;
+
+        debug!(count = ids.len(), "listings purged");
+
+        if self.redis.is_some() {
+            for listing in expired {
+                match Listing::try_from(listing) {
+                    Ok(listing) => publish_change(self, ListingAction::Deleted, &listing).await,
+                    Err(e) => error!("{e}"),
+                }
+            }
+        }
+
+        Ok(ids.len())
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn dispatch_outbox(&self, batch: usize) -> Result<usize, CoreError> {
+        outbox::dispatch(self, batch).await
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn transition_listing(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid,
+        status: ListingStatus,
+    ) -> Result<Option<Listing>, CoreError> {
+        let listing_id = (Collection::Listing.to_string(), id.to_string());
+
+        let current: Option<DatabaseEntityListing> = self
+            .client
+            .select(listing_id.clone())
+            .await
+            .map_err(map_db_error)?;
+
+        let current = match current {
+            Some(current) if current.deleted.is_none() => current.status,
+            _ => return Ok(None),
+        };
+        check_listing_owner(self, id, user_id).await?;
+        let status = current.transition_to(status)?;
+
+        let event_id = Uuid::now_v7();
+
+        // only apply the move if nobody changed the status in the meantime
+        let mut item = self
+            .client
+            .query(concat!(
+                "BEGIN TRANSACTION;
+                LET $listing = (UPDATE type::thing($table, $id) SET
+                    status = type::string($status),
+                    updated = time::now()
+                WHERE status = type::string($current) AND !deleted
+                RETURN AFTER)[0];",
+                record_change!(),
+                "RETURN $listing;
+                COMMIT TRANSACTION;",
+            ))
+            .bind(("table", Collection::Listing))
+            .bind(("id", id.to_string()))
+            .bind(("status", status))
+            .bind(("current", current))
+            .bind(("previous_status", current))
+            .bind(("user_tbl", Collection::User))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("outbox_tbl", Collection::Outbox))
+            .bind(("event_id", event_id.to_string()))
+            .bind(("action", ListingAction::Updated))
+            .await
+            .map_err(map_db_error)?;
+
+        let item: Option<DatabaseEntityListing> = item.take(0).map_err(map_db_error)?;
+
+        match item {
+            Some(e) => {
+                let listing = Listing::try_from(e)?;
+                outbox::deliver_now(self, &event_id).await;
+                publish_change(self, ListingAction::Updated, &listing).await;
+                debug!(%status, "listing transitioned");
+                Ok(Some(listing))
+            }
+            None => Err(CoreError::Database(format!(
+                "listing: {id} was modified while moving to {status}"
+            ))),
+        }
+    }
+
     #[instrument(skip(self, files), err(Debug))]
     async fn upload_images(&self, files: &[&[u8]]) -> Result<Vec<(String, String)>, CoreError> {
         let bucket = &self.storage_bucket;
@@ -249,19 +528,20 @@ impl MutateListings for Client {
     }
 }
 
-#[derive(serde::Serialize)]
+/// Object key of an uploaded image, which may be stored as a bare id or a full url
+fn image_key(image: &str) -> String {
+    format!("/{}", image.rsplit('/').next().unwrap_or(image))
+}
+
+/// The fields of a listing its seller sets. The rest are kept by the database
 struct InputListing<'a> {
     title: &'a str,
     description: &'a str,
     price: &'a Decimal,
     image_url: &'a str,
     other_images: &'a [String],
-    active: bool,
     negotiable: bool,
-    created_at: &'a OffsetDateTime,
-    updated_at: &'a OffsetDateTime,
-    expires_at: Option<&'a OffsetDateTime>,
-    deleted_at: Option<&'a OffsetDateTime>,
+    expires: Option<&'a OffsetDateTime>,
 }
 
 impl<'a> From<&'a Listing> for InputListing<'a> {
@@ -272,12 +552,8 @@ impl<'a> From<&'a Listing> for InputListing<'a> {
             description: &value.description,
             price: &value.price,
             other_images: &value.other_images,
-            active: value.published,
             negotiable: value.negotiable,
-            created_at: &value.created,
-            deleted_at: value.deleted.as_ref(),
-            updated_at: &value.updated,
-            expires_at: value.expires.as_ref(),
+            expires: value.expires.as_ref(),
         }
     }
 }
@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use api_core::{api::CoreError, reexports::uuid::Uuid, Listing};
+use serde::Deserialize;
+use surrealdb::opt::RecordId;
+use tracing::{debug, error, instrument, warn};
+
+use crate::{
+    collections::Collection,
+    entity::{create_thing_from_id, listing::DatabaseEntityListing, outbox::DatabaseEntityOutbox},
+    map_db_error,
+    query::LISTINGS_INDEX,
+    redis::{
+        dependency::{self, Dependency},
+        local_cache::LocalCache,
+        pubsub, RedisPool,
+    },
+    webhook, Client,
+};
+
+/// Records the change made to `$listing` by the statements before it, if one was made, so it is
+/// delivered even when the process stops right after the write. Expects `$outbox_tbl`,
+/// `$event_id`, `$action`, `$user_tbl` and `$user_id` to be bound, along with
+/// `$previous_status` when the change moved the listing to another status. The listing's
+/// category and condition are recorded too, as cached entries may depend on either
+macro_rules! record_change {
+    () => {
+        "IF $listing {
+            CREATE type::thing($outbox_tbl, $event_id) CONTENT {
+                action: $action,
+                listing: $listing,
+                seller: type::thing($user_tbl, $user_id),
+                category: (SELECT VALUE out FROM inCategory WHERE in = $listing.id)[0],
+                condition: (SELECT VALUE out FROM withCondition WHERE in = $listing.id)[0],
+                previous_status: $previous_status,
+                attempts: 0,
+                created: time::now(),
+                available: time::now()
+            };
+        };"
+    };
+}
+pub(crate) use record_change;
+
+/// How long a claimed change is left alone before another dispatcher may retry it
+pub(crate) const LEASE: Duration = Duration::from_secs(60);
+
+/// The longest a change that keeps failing waits between attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+pub(crate) fn backoff(attempts: u32) -> Duration {
+    Duration::from_secs(2_u64.saturating_pow(attempts)).min(MAX_BACKOFF)
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DueEntry {
+    pub id: RecordId,
+}
+
+/// Delivers up to `batch` of the changes that are due, oldest first
+pub(crate) async fn dispatch(client: &Client, batch: usize) -> Result<usize, CoreError> {
+    let mut due = client
+        .client
+        .query(
+            "SELECT id, created FROM type::table($table)
+            WHERE available <= time::now()
+            ORDER BY created
+            LIMIT $batch",
+        )
+        .bind(("table", Collection::Outbox))
+        .bind(("batch", batch))
+        .await
+        .map_err(map_db_error)?;
+
+    let due: Vec<DueEntry> = due.take(0).map_err(map_db_error)?;
+    if due.is_empty() {
+        return Ok(0);
+    }
+
+    let ids: Vec<_> = due.into_iter().map(|entry| entry.id).collect();
+    deliver_entries(client, &ids).await
+}
+
+/// Delivers a change right after the write that recorded it. Whatever fails here is left for
+/// the dispatcher to retry
+pub(crate) async fn deliver_now(client: &Client, event_id: &Uuid) {
+    let id = create_thing_from_id(Collection::Outbox, event_id);
+
+    if let Err(e) = deliver_entries(client, &[id]).await {
+        error!("{e}");
+    }
+}
+
+/// Claims the given changes and delivers them, removing each one that was delivered. Claiming
+/// pushes a change out of reach of every other dispatcher until its lease runs out, so a
+/// change is only ever delivered again if delivering it failed or took too long
+#[instrument(skip(client), err(Debug))]
+async fn deliver_entries(client: &Client, ids: &[RecordId]) -> Result<usize, CoreError> {
+    let mut claimed = client
+        .client
+        .query(
+            "UPDATE $ids SET
+                attempts += 1,
+                available = time::now() + type::duration($lease)
+            WHERE available <= time::now()
+            RETURN AFTER",
+        )
+        .bind(("ids", ids))
+        .bind(("lease", format!("{}s", LEASE.as_secs())))
+        .await
+        .map_err(map_db_error)?;
+
+    let claimed: Vec<DatabaseEntityOutbox> = claimed.take(0).map_err(map_db_error)?;
+
+    let mut delivered = 0;
+    for entry in claimed {
+        match deliver(client, &entry).await {
+            Ok(()) => {
+                client
+                    .client
+                    .query("DELETE $id")
+                    .bind(("id", &entry.id))
+                    .await
+                    .map_err(map_db_error)?
+                    .check()
+                    .map_err(map_db_error)?;
+                delivered += 1;
+            }
+            Err(e) => {
+                let retry_in = backoff(entry.attempts);
+                warn!(
+                    id = %entry.id,
+                    attempts = entry.attempts,
+                    retry_in = ?retry_in,
+                    "listing change not delivered: {e}"
+                );
+                client
+                    .client
+                    .query(
+                        "UPDATE $id SET
+                            available = time::now() + type::duration($retry_in),
+                            error = $error",
+                    )
+                    .bind(("id", &entry.id))
+                    .bind(("retry_in", format!("{}s", retry_in.as_secs())))
+                    .bind(("error", e.to_string()))
+                    .await
+                    .map_err(map_db_error)?
+                    .check()
+                    .map_err(map_db_error)?;
+            }
+        }
+    }
+
+    debug!(delivered, "listing changes delivered");
+    Ok(delivered)
+}
+
+async fn deliver(client: &Client, entry: &DatabaseEntityOutbox) -> Result<(), CoreError> {
+    let listing_id = entry.listing_id()?;
+
+    if let Some((ref redis, _ttl)) = client.redis {
+        clear_listing_cache(redis, client.local_cache.as_ref(), entry).await?;
+    }
+    if let Some(ref search) = client.search_client {
+        reindex(client, search, &listing_id).await?;
+    }
+
+    // queued last, so the change is only queued again if removing it from the outbox fails
+    webhook::enqueue(client, entry).await
+}
+
+/// Clears every cached entry depending on the listing, on who sells it or on what it is filed
+/// under. Other replicas drop the entries they keep in process as redis publishes the deletions
+async fn clear_listing_cache(
+    redis: &RedisPool,
+    local: Option<&LocalCache>,
+    entry: &DatabaseEntityOutbox,
+) -> Result<(), CoreError> {
+    let id = entry.listing_id()?;
+    let seller = entry.seller_id()?;
+    let category = entry.category_id()?;
+    let condition = entry.condition_id()?;
+
+    let mut dependencies = vec![Dependency::Listings, Dependency::Listing { id: &id }];
+    dependencies.extend(seller.as_ref().map(|id| Dependency::User { id }));
+    dependencies.extend(category.as_ref().map(|id| Dependency::Category { id }));
+    dependencies.extend(condition.as_ref().map(|id| Dependency::Condition { id }));
+
+    let keys = dependency::invalidate(redis, &dependencies)
+        .await
+        .map_err(|e| CoreError::Other(e.to_string()))?;
+
+    if let Some(local) = local {
+        local.remove(&keys);
+    }
+    pubsub::publish_invalidated(redis, &keys).await;
+
+    Ok(())
+}
+
+/// Brings the search index in line with the listing as it is now rather than as it was
+/// recorded, so changes delivered out of order or more than once leave it correct
+async fn reindex(
+    client: &Client,
+    search: &meilisearch_sdk::Client,
+    id: &Uuid,
+) -> Result<(), CoreError> {
+    let current: Option<DatabaseEntityListing> = client
+        .client
+        .select((Collection::Listing.to_string(), id.to_string()))
+        .await
+        .map_err(map_db_error)?;
+
+    let index = search.index(LISTINGS_INDEX);
+    let task = match current.filter(|listing| listing.deleted.is_none()) {
+        Some(listing) => {
+            let listing = Listing::try_from(listing)?;
+            index.add_documents(&[listing], Some("id")).await
+        }
+        None => index.delete_document(id).await,
+    };
+
+    task.map(|_| ())
+        .map_err(|e| CoreError::Other(e.to_string()))
+}
@@ -1,52 +1,52 @@
+use std::collections::HashMap;
+
 use api_core::{
     api::{CoreError, QueryListingCondition},
+    reexports::uuid::Uuid,
     ListingCondition,
 };
-use tracing::{error, instrument};
+use tracing::instrument;
 
 use crate::{
     collections::Collection,
-    entity::condition::DatabaseEntityListingCondition,
+    entity::{
+        condition::{DatabaseEntityListingCondition, DatabaseEntityListingWithCondition},
+        create_thing_from_id,
+    },
     map_db_error,
     redis::{cache_keys::CacheKey, redis_query},
     Client,
 };
 
+async fn select_conditions(db: &Client) -> Result<Vec<ListingCondition>, CoreError> {
+    let conditions: Vec<DatabaseEntityListingCondition> = db
+        .client
+        .select(Collection::ListingCondition)
+        .await
+        .map_err(map_db_error)?;
+
+    conditions
+        .into_iter()
+        .map(ListingCondition::try_from)
+        .collect()
+}
+
 async fn db_get_conditions(db: &Client) -> Result<std::vec::IntoIter<ListingCondition>, CoreError> {
+    // conditions are not changed here, so they only ever expire
     let conditions = if let Some((ref redis, ttl)) = db.redis {
-        let cache_key = CacheKey::AllConditions;
-        let conditions = redis_query::query::<Vec<ListingCondition>>(cache_key, redis).await;
-
-        if let Some(conditions) = conditions {
-            conditions
-        } else {
-            let conditions: Vec<DatabaseEntityListingCondition> = db
-                .client
-                .select(Collection::ListingCondition)
-                .await
-                .map_err(map_db_error)?;
-
-            let conditions = conditions
-                .into_iter()
-                .map(ListingCondition::try_from)
-                .collect::<Result<Vec<ListingCondition>, CoreError>>()?;
-
-            if let Err(e) = redis_query::update(cache_key, redis, &conditions, ttl).await {
-                error!(key = %cache_key, "[redis update]: {e}");
-            }
-
-            conditions
-        }
+        let client = db.clone();
+        redis_query::fetch(
+            redis,
+            ttl,
+            &db.flights,
+            db.local_cache.as_ref(),
+            CacheKey::AllConditions,
+            &[],
+            move || async move { select_conditions(&client).await },
+        )
+        .await?
     } else {
-        let conditions: Vec<DatabaseEntityListingCondition> = db
-            .client
-            .select(Collection::ListingCondition)
-            .await
-            .map_err(map_db_error)?;
-        conditions
-            .into_iter()
-            .map(ListingCondition::try_from)
-            .collect::<Result<Vec<ListingCondition>, CoreError>>()?
+        select_conditions(db).await?
     };
 
     Ok(conditions.into_iter())
@@ -59,4 +59,34 @@ impl QueryListingCondition for Client {
     ) -> Result<impl ExactSizeIterator<Item = ListingCondition>, CoreError> {
         db_get_conditions(self).await
     }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_conditions_by_listing_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, ListingCondition>, CoreError> {
+        let ids: Vec<_> = listing_ids
+            .iter()
+            .map(|id| create_thing_from_id(Collection::Listing, id))
+            .collect();
+
+        let mut conditions = self
+            .client
+            .query(
+                "SELECT id, (->withCondition->listing_condition)[0] AS condition \
+                 FROM type::table($table) WHERE id INSIDE $ids FETCH condition",
+            )
+            .bind(("table", Collection::Listing))
+            .bind(("ids", ids))
+            .await
+            .map_err(map_db_error)?;
+
+        let conditions: Vec<DatabaseEntityListingWithCondition> =
+            conditions.take(0).map_err(map_db_error)?;
+
+        conditions
+            .into_iter()
+            .filter_map(|listing| listing.into_condition().transpose())
+            .collect()
+    }
 }
@@ -0,0 +1,248 @@
+use api_core::{
+    api::CoreError, Listing, ListingFilter, ListingKey, ListingSort, Page, PageRequest, SortValue,
+};
+use tracing::instrument;
+
+use crate::{
+    collections::Collection,
+    entity::{create_thing_from_id, listing::DatabaseEntityListing},
+    map_db_error, Client,
+};
+
+/// A SurrealQL page query and the matching count query, along with the string parameters
+/// they expect
+pub(crate) struct CompiledQuery {
+    pub(crate) sql: String,
+    pub(crate) count_sql: String,
+    pub(crate) bindings: Vec<(&'static str, String)>,
+}
+
+/// Where listings without an expiry are placed when ordering by expiry
+const NEVER_EXPIRES: &str = "d'9999-12-31T23:59:59Z'";
+
+/// The expression listings are ordered by and whether it ascends
+fn sort_column(sort: ListingSort) -> (&'static str, bool) {
+    match sort {
+        ListingSort::CreatedDesc => ("created", false),
+        ListingSort::CreatedAsc => ("created", true),
+        ListingSort::PriceAsc => ("price", true),
+        ListingSort::PriceDesc => ("price", false),
+        ListingSort::ExpiresAsc => ("expires_order", true),
+    }
+}
+
+/// Compiles a filter into a single parameterised query over listings that have not been
+/// deleted. Conditions are only added for the fields that are set and every value is bound,
+/// never interpolated. Tags are record ids and are bound by the caller as `$tags`, and the
+/// page size as `$limit`.
+///
+/// The page is bounded by the keys on either side of it. Paging from the end reverses the
+/// order, so the caller has to reverse the listings it reads back
+pub(crate) fn compile(
+    filter: &ListingFilter,
+    sort: ListingSort,
+    page: &PageRequest,
+) -> CompiledQuery {
+    let mut conditions = vec![String::from("!deleted")];
+    let mut bindings = Vec::new();
+
+    let mut relation = |edge: &str, collection: Collection, param: &'static str, id: String| {
+        conditions.push(format!(
+            "{edge}{collection} CONTAINS type::thing('{collection}', ${param})"
+        ));
+        bindings.push((param, id));
+    };
+
+    if let Some(category_id) = filter.category_id {
+        relation(
+            "->inCategory->",
+            Collection::Category,
+            "category_id",
+            category_id.to_string(),
+        );
+    }
+    if let Some(user_id) = filter.user_id {
+        relation(
+            "<-sells<-",
+            Collection::User,
+            "user_id",
+            user_id.to_string(),
+        );
+    }
+    if let Some(condition_id) = filter.condition_id {
+        relation(
+            "->withCondition->",
+            Collection::ListingCondition,
+            "condition_id",
+            condition_id.to_string(),
+        );
+    }
+
+    if !filter.tags.is_empty() {
+        conditions.push(String::from("tags CONTAINSANY $tags"));
+    }
+
+    let mut compare = |condition: &str, param: &'static str, value: String| {
+        conditions.push(condition.to_owned());
+        bindings.push((param, value));
+    };
+
+    if let Some(min) = filter.min_price {
+        compare(
+            "price >= type::decimal($min_price)",
+            "min_price",
+            min.to_string(),
+        );
+    }
+    if let Some(max) = filter.max_price {
+        compare(
+            "price <= type::decimal($max_price)",
+            "max_price",
+            max.to_string(),
+        );
+    }
+    if let Some(negotiable) = filter.negotiable {
+        compare(
+            "negotiable = type::bool($negotiable)",
+            "negotiable",
+            negotiable.to_string(),
+        );
+    }
+    if let Some(status) = filter.status {
+        compare(
+            "status = type::string($status)",
+            "status",
+            status.to_string(),
+        );
+    }
+
+    let windows = [
+        ("created >=", "created_after", filter.created_after),
+        ("created <=", "created_before", filter.created_before),
+        ("expires >=", "expires_after", filter.expires_after),
+        ("expires <=", "expires_before", filter.expires_before),
+    ];
+    for (comparison, param, value) in windows {
+        if let Some(value) = value {
+            compare(
+                &format!("{comparison} time::from::nanos(type::int(${param}))"),
+                param,
+                value.unix_timestamp_nanos().to_string(),
+            );
+        }
+    }
+
+    // the total covers the whole result set, not just this page
+    let count_sql = format!(
+        "SELECT count() AS total FROM type::table($table) WHERE {} GROUP ALL",
+        conditions.join(" AND ")
+    );
+
+    let (column, ascending) = sort_column(sort);
+    // the alias is not visible to WHERE
+    let expression = match sort {
+        ListingSort::ExpiresAsc => format!("(expires ?? {NEVER_EXPIRES})"),
+        _ => column.to_owned(),
+    };
+
+    // ties are broken by id, matching `ListingSort::compare_keys`
+    let mut bound = |key: &ListingKey, after: bool, value_param: &'static str, id_param| {
+        let value = match key.value {
+            SortValue::Created(value) | SortValue::Expires(Some(value)) => {
+                bindings.push((value_param, value.unix_timestamp_nanos().to_string()));
+                format!("time::from::nanos(type::int(${value_param}))")
+            }
+            SortValue::Price(value) => {
+                bindings.push((value_param, value.to_string()));
+                format!("type::decimal(${value_param})")
+            }
+            SortValue::Expires(None) => NEVER_EXPIRES.to_owned(),
+        };
+        bindings.push((id_param, key.id.to_string()));
+
+        let greater = |greater: bool| if greater { ">" } else { "<" };
+        let (value_op, id_op) = (greater(ascending == after), greater(after));
+        conditions.push(format!(
+            "({expression} {value_op} {value} OR ({expression} = {value} \
+             AND id {id_op} type::thing($table, ${id_param})))"
+        ));
+    };
+
+    if let Some(after) = &page.after {
+        bound(after, true, "after_value", "after_id");
+    }
+    if let Some(before) = &page.before {
+        bound(before, false, "before_value", "before_id");
+    }
+
+    let direction = |ascending: bool| if ascending { "ASC" } else { "DESC" };
+    let order = format!(
+        "{column} {}, id {}",
+        direction(ascending != page.from_end),
+        direction(!page.from_end)
+    );
+    let projection = match sort {
+        // listings that never expire come last
+        ListingSort::ExpiresAsc => format!("*, {expression} AS {column}"),
+        _ => String::from("*"),
+    };
+
+    CompiledQuery {
+        sql: format!(
+            "SELECT {projection} FROM type::table($table) WHERE {} \
+             ORDER BY {order} LIMIT $limit",
+            conditions.join(" AND ")
+        ),
+        count_sql,
+        bindings,
+    }
+}
+
+#[instrument(skip(db), err(Debug))]
+pub(super) async fn find_listings(
+    db: &Client,
+    filter: &ListingFilter,
+    sort: ListingSort,
+    page: PageRequest,
+) -> Result<Page<Listing>, CoreError> {
+    let CompiledQuery {
+        sql,
+        count_sql,
+        bindings,
+    } = compile(filter, sort, &page);
+
+    let tags: Vec<_> = filter
+        .tags
+        .iter()
+        .map(|tag| create_thing_from_id(Collection::Tag, tag))
+        .collect();
+
+    let mut query = db
+        .client
+        .query(sql)
+        .query(count_sql)
+        .bind(("table", Collection::Listing))
+        .bind(("tags", tags))
+        .bind(("limit", page.limit));
+    for binding in bindings {
+        query = query.bind(binding);
+    }
+
+    let mut response = query.await.map_err(map_db_error)?;
+    let listings: Vec<DatabaseEntityListing> = response.take(0).map_err(map_db_error)?;
+    // no row is returned when nothing matches
+    let total: Option<usize> = response.take((1, "total")).map_err(map_db_error)?;
+
+    let mut items = listings
+        .into_iter()
+        .map(Listing::try_from)
+        .collect::<Result<Vec<Listing>, CoreError>>()?;
+    if page.from_end {
+        items.reverse();
+    }
+
+    Ok(Page {
+        items,
+        total: total.unwrap_or_default(),
+    })
+}
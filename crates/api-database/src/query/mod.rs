@@ -1,67 +1,173 @@
 mod condition;
+pub(crate) mod filter;
+
+use std::collections::HashMap;
 
 use api_core::{
-    api::{CoreError, QueryListings},
+    api::{CoreError, QueryListings, SubscribeListings},
     reexports::uuid::Uuid,
-    Listing,
+    Listing, ListingAction, ListingEdges, ListingEvent, ListingFilter, ListingSort, ListingStatus,
+    Page, PageRequest,
+};
+use futures_util::{
+    future::{ready, Either},
+    Stream, StreamExt,
 };
-use futures_util::{Stream, StreamExt};
 use meilisearch_sdk::{SearchQuery, SearchResults};
-use surrealdb::Notification;
+use rust_decimal::Decimal;
+use surrealdb::{opt::RecordId, Action, Notification};
 use tracing::{debug, error, instrument};
 
 use crate::{
     collections::Collection,
-    entity::{create_thing_from_id, listing::DatabaseEntityListing},
+    entity::{
+        create_string_from_id, create_thing_from_id,
+        listing::{DatabaseEntityListing, DatabaseEntityListingEdges},
+    },
     map_db_error,
-    redis::{cache_keys::CacheKey, redis_query},
+    redis::{cache_keys::CacheKey, dependency::Dependency, redis_query},
     Client,
 };
 
-async fn db_get_listings(
+/// The meilisearch index listings are searched in
+pub(crate) const LISTINGS_INDEX: &str = "listings";
+
+/// Selects every listing that has not been deleted
+async fn select_listings(db: &Client) -> Result<Vec<Listing>, CoreError> {
+    let mut listings = db
+        .client
+        .query("SELECT * FROM type::table($table) WHERE !deleted")
+        .bind(("table", Collection::Listing))
+        .await
+        .map_err(map_db_error)?;
+
+    let listings: Vec<DatabaseEntityListing> = listings.take(0).map_err(map_db_error)?;
+    listings.into_iter().map(Listing::try_from).collect()
+}
+
+/// Selects every listing that has not been deleted and matches `filter`, which is given the
+/// record in `value`
+async fn select_listings_where(
     db: &Client,
-    wait_for_indexing: bool,
-) -> Result<std::vec::IntoIter<Listing>, CoreError> {
-    let listings = if let Some((ref redis, ttl)) = db.redis {
-        let cache_key = CacheKey::AllListings;
-        let listings = redis_query::query::<Vec<Listing>>(cache_key, redis).await;
+    filter: &str,
+    value: RecordId,
+) -> Result<Vec<Listing>, CoreError> {
+    let mut listings = db
+        .client
+        .query(format!(
+            "SELECT * FROM type::table($table) WHERE {filter} AND !deleted"
+        ))
+        .bind(("table", Collection::Listing))
+        .bind(("value", value))
+        .await
+        .map_err(map_db_error)?;
+
+    let listings: Vec<DatabaseEntityListing> = listings.take(0).map_err(map_db_error)?;
+    listings.into_iter().map(Listing::try_from).collect()
+}
 
-        if let Some(listings) = listings {
-            listings
-        } else {
-            let listings: Vec<DatabaseEntityListing> = db
-                .client
-                .select(Collection::Listing)
-                .await
-                .map_err(map_db_error)?;
+/// Selects every listing that has not been deleted and carries any of the tags
+async fn select_listings_with_tags(db: &Client, tags: &[Uuid]) -> Result<Vec<Listing>, CoreError> {
+    let tags: Vec<_> = tags
+        .iter()
+        .map(|f| create_thing_from_id(Collection::Tag, f))
+        .collect();
+
+    let mut listings = db
+        .client
+        .query("SELECT * FROM type::table($table) WHERE tags CONTAINSANY type::array($values) AND !deleted")
+        .bind(("table", Collection::Listing))
+        .bind(("values", &tags))
+        .await
+        .map_err(map_db_error)?;
+
+    let listings: Vec<DatabaseEntityListing> = listings.take(0).map_err(map_db_error)?;
+    listings.into_iter().map(Listing::try_from).collect()
+}
 
-            let listings = listings
-                .into_iter()
-                .map(Listing::try_from)
-                .collect::<Result<Vec<Listing>, CoreError>>()?;
+/// Selects every listing that has not been deleted and is priced between `min` and `max`
+async fn select_listings_in_price_range(
+    db: &Client,
+    min: &Decimal,
+    max: &Decimal,
+) -> Result<Vec<Listing>, CoreError> {
+    let mut listings = db
+        .client
+        .query("SELECT * FROM type::table($table) WHERE price >= type::decimal($min) AND price <= type::decimal($max) AND !deleted")
+        .bind(("table", Collection::Listing))
+        .bind(("min", min))
+        .bind(("max", max))
+        .await
+        .map_err(map_db_error)?;
+
+    let listings: Vec<DatabaseEntityListing> = listings.take(0).map_err(map_db_error)?;
+    listings.into_iter().map(Listing::try_from).collect()
+}
 
-            if let Err(e) = redis_query::update(cache_key, redis, &listings, ttl).await {
-                error!(key = %cache_key, "[redis update]: {e}");
+/// Selects a listing, unless it was deleted
+async fn select_listing(db: &Client, listing_id: &Uuid) -> Result<Option<Listing>, CoreError> {
+    let id = create_thing_from_id(Collection::Listing, listing_id);
+    let listing: Option<DatabaseEntityListing> =
+        db.client.select(id).await.map_err(map_db_error)?;
+
+    Ok(listing
+        .filter(|f| f.deleted.is_none())
+        .and_then(|f| match Listing::try_from(f) {
+            Ok(cat) => Some(cat),
+            Err(e) => {
+                error!("{e}");
+                None
             }
+        }))
+}
 
-            listings
-        }
+/// Selects the listings of `listing_ids` that have not been deleted
+async fn select_listings_by_ids(
+    db: &Client,
+    listing_ids: &[Uuid],
+) -> Result<Vec<Listing>, CoreError> {
+    let ids: Vec<RecordId> = listing_ids
+        .iter()
+        .map(|id| create_thing_from_id(Collection::Listing, id))
+        .collect();
+
+    let mut listings = db
+        .client
+        .query("SELECT * FROM type::table($table) WHERE id INSIDE $ids AND !deleted")
+        .bind(("table", Collection::Listing))
+        .bind(("ids", ids))
+        .await
+        .map_err(map_db_error)?;
+
+    let listings: Vec<DatabaseEntityListing> = listings.take(0).map_err(map_db_error)?;
+
+    listings.into_iter().map(Listing::try_from).collect()
+}
+
+async fn db_get_listings(
+    db: &Client,
+    wait_for_indexing: bool,
+) -> Result<std::vec::IntoIter<Listing>, CoreError> {
+    let listings = if let Some((ref redis, ttl)) = db.redis {
+        let client = db.clone();
+        redis_query::fetch(
+            redis,
+            ttl,
+            &db.flights,
+            db.local_cache.as_ref(),
+            CacheKey::AllListings,
+            &[Dependency::Listings],
+            move || async move { select_listings(&client).await },
+        )
+        .await?
     } else {
-        let listings: Vec<DatabaseEntityListing> = db
-            .client
-            .select(Collection::Listing)
-            .await
-            .map_err(map_db_error)?;
-        listings
-            .into_iter()
-            .map(Listing::try_from)
-            .collect::<Result<Vec<Listing>, CoreError>>()?
+        select_listings(db).await?
     };
 
     if let Some(ref client) = db.search_client {
         debug!("indexing listings for search");
-        let index = client.index("listings");
-        let res = index
+        let res = client
+            .index(LISTINGS_INDEX)
             .add_documents(&listings, Some("id"))
             .await
             .map_err(|e| CoreError::Other(e.to_string()))?;
@@ -80,93 +186,154 @@ async fn get_listings_by_field(
     field: &str,
     id: &Uuid,
 ) -> Result<std::vec::IntoIter<Listing>, CoreError> {
-    let collection = Collection::from(field);
+    let collection = Collection::try_from(field)?;
     let field_id_value = create_thing_from_id(collection, id);
-    // TODO: cache_key from coll
-    let cache_key = match collection {
-        Collection::Listing => todo!(),
-        Collection::User => CacheKey::UserListing { user_id: id },
-        Collection::Tag => todo!(),
-        Collection::ListingCondition => todo!(),
-        Collection::Category => todo!(),
+    // tags can be added to any listing, so listings by tag depend on every listing
+    let (cache_key, dependency) = match collection {
+        Collection::User => (
+            CacheKey::UserListing { user_id: id },
+            Dependency::User { id },
+        ),
+        Collection::Category => (
+            CacheKey::CategoryListing { category_id: id },
+            Dependency::Category { id },
+        ),
+        Collection::ListingCondition => (
+            CacheKey::ConditionListing { condition_id: id },
+            Dependency::Condition { id },
+        ),
+        Collection::Tag => (
+            CacheKey::TagListing {
+                tags: std::slice::from_ref(&id),
+            },
+            Dependency::Listings,
+        ),
+        _ => {
+            return Err(CoreError::Database(format!(
+                "listings cannot be looked up by {collection}"
+            )))
+        }
     };
 
-    if let Some((ref redis, ttl)) = db.redis {
-        let listings = redis_query::query::<Vec<Listing>>(cache_key, redis).await;
+    // sellers are related to their listings through `sells` edges, as are listings to their
+    // category and condition through edges of their own
+    let filter = match collection {
+        Collection::User => "<-sells<-user CONTAINS $value",
+        Collection::Category => "->inCategory->category CONTAINS $value",
+        Collection::ListingCondition => "->withCondition->listing_condition CONTAINS $value",
+        _ => "tags CONTAINS $value",
+    };
 
-        if let Some(listings) = listings {
-            Ok(listings.into_iter())
-        } else {
-            let mut listings = db
-                .client
-                .query(format!(
-                    "SELECT * FROM type::table($table) WHERE {field} = type::string($value)"
-                ))
-                .bind(("table", Collection::Listing))
-                .bind(("value", field_id_value))
-                .await
-                .map_err(map_db_error)?;
+    let listings = if let Some((ref redis, ttl)) = db.redis {
+        let client = db.clone();
+        redis_query::fetch(
+            redis,
+            ttl,
+            &db.flights,
+            db.local_cache.as_ref(),
+            cache_key,
+            &[dependency],
+            move || async move { select_listings_where(&client, filter, field_id_value).await },
+        )
+        .await?
+    } else {
+        select_listings_where(db, filter, field_id_value).await?
+    };
 
-            let listings: Vec<DatabaseEntityListing> = listings.take(0).map_err(map_db_error)?;
+    Ok(listings.into_iter())
+}
 
-            let items: Result<Vec<Listing>, _> =
-                listings.into_iter().map(Listing::try_from).collect();
+/// Turns a live query notification into the change it stands for
+async fn into_event(
+    notification: Result<Notification<DatabaseEntityListing>, surrealdb::Error>,
+) -> Option<ListingEvent> {
+    match notification {
+        Ok(f) => {
+            let action = match f.action {
+                Action::Create => ListingAction::Created,
+                // listings are soft deleted, so they are only ever updated
+                Action::Update if f.data.deleted.is_some() => ListingAction::Deleted,
+                Action::Update => ListingAction::Updated,
+                Action::Delete => ListingAction::Deleted,
+                action => {
+                    error!("unknown live query action: {action:?}");
+                    return None;
+                }
+            };
+            match Listing::try_from(f.data) {
+                Ok(d) => Some(ListingEvent::new(action, d)),
+                Err(e) => {
+                    error!("{e:?}");
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            error!("{e:?}");
+            None
+        }
+    }
+}
 
-            let listings = items?;
+impl SubscribeListings for Client {
+    /// Changes come from redis once it is set up, so subscribers hear about the ones made on
+    /// every replica. Otherwise they come from a live query
+    #[instrument(skip(self), err(Debug))]
+    async fn live_listings(
+        &self,
+    ) -> Result<impl Stream<Item = ListingEvent> + Send + '_, CoreError> {
+        if let Some(ref changes) = self.changes {
+            let changes = changes
+                .subscribe()
+                .await
+                .map_err(|e| CoreError::Other(e.to_string()))?;
 
-            if let Err(e) = redis_query::update(cache_key, redis, &listings, ttl).await {
-                error!(key = %cache_key, "[redis update]: {e}");
-            }
-            Ok(listings.into_iter())
+            return Ok(Either::Left(changes));
         }
-    } else {
-        let mut listings = db
+
+        let streams = self
             .client
-            .query(format!(
-                "SELECT * FROM type::table($table) WHERE {field} = type::string($value)"
-            ))
-            .bind(("table", Collection::Listing))
-            .bind(("value", field_id_value))
+            .select(Collection::Listing)
+            .live()
             .await
             .map_err(map_db_error)?;
 
-        let listings: Vec<DatabaseEntityListing> = listings.take(0).map_err(map_db_error)?;
-
-        let items: Result<Vec<Listing>, _> = listings.into_iter().map(Listing::try_from).collect();
+        Ok(Either::Right(streams.filter_map(into_event)))
+    }
 
-        let listings = items?;
+    #[instrument(skip(self), err(Debug))]
+    async fn watch_listing(
+        &self,
+        listing_id: Uuid,
+    ) -> Result<impl Stream<Item = ListingEvent> + Send + '_, CoreError> {
+        if let Some(ref changes) = self.changes {
+            let changes = changes
+                .subscribe()
+                .await
+                .map_err(|e| CoreError::Other(e.to_string()))?;
 
-        Ok(listings.into_iter())
-    }
-}
+            return Ok(Either::Left(
+                changes.filter(move |event| ready(event.listing.id == listing_id)),
+            ));
+        }
 
-impl Client {
-    pub async fn live_listings(&self) -> Result<impl Stream<Item = Listing> + '_, CoreError> {
-        let streams = self
+        // live queries keep their condition around, so the id is written into the query rather
+        // than bound as a parameter of this session
+        let id = create_thing_from_id(Collection::Listing, &listing_id);
+        let mut response = self
             .client
-            .select(Collection::Listing)
-            .live()
+            .query(format!(
+                "LIVE SELECT * FROM {} WHERE id = {id}",
+                Collection::Listing
+            ))
             .await
             .map_err(map_db_error)?;
 
-        Ok(streams.filter_map(|f| async move {
-            match f {
-                Ok(f) => {
-                    let f: Notification<DatabaseEntityListing> = f;
-                    match Listing::try_from(f.data) {
-                        Ok(d) => Some(d),
-                        Err(e) => {
-                            error!("{e:?}");
-                            None
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("{e:?}");
-                    None
-                }
-            }
-        }))
+        let stream = response
+            .stream::<Notification<DatabaseEntityListing>>(0)
+            .map_err(map_db_error)?;
+
+        Ok(Either::Right(stream.filter_map(into_event)))
     }
 }
 
@@ -178,43 +345,124 @@ impl QueryListings for Client {
 
     #[instrument(skip(self), err(Debug))]
     async fn get_listing_by_id(&self, listing_id: &Uuid) -> Result<Option<Listing>, CoreError> {
-        let id = create_thing_from_id(Collection::Listing, listing_id);
         if let Some((ref redis, ttl)) = self.redis {
-            let cache_key = CacheKey::Listing { id: listing_id };
-
-            let listing = redis_query::query::<Option<Listing>>(cache_key, redis).await;
-
-            if let Some(listing) = listing {
-                Ok(listing)
-            } else {
-                let listing: Option<DatabaseEntityListing> =
-                    self.client.select(id).await.map_err(map_db_error)?;
-                let listing = listing.and_then(|f| match Listing::try_from(f) {
-                    Ok(cat) => Some(cat),
-                    Err(e) => {
-                        error!("{e}");
-                        None
-                    }
-                });
-
-                if let Err(e) = redis_query::update(cache_key, redis, listing.as_ref(), ttl).await {
-                    error!(key = %cache_key, "[redis update]: {e}");
-                }
-                Ok(listing)
-            }
+            let db = self.clone();
+            let id = *listing_id;
+            redis_query::fetch(
+                redis,
+                ttl,
+                &self.flights,
+                self.local_cache.as_ref(),
+                CacheKey::Listing { id: listing_id },
+                &[Dependency::Listing { id: listing_id }],
+                move || async move { select_listing(&db, &id).await },
+            )
+            .await
         } else {
-            let listing: Option<DatabaseEntityListing> =
-                self.client.select(id).await.map_err(map_db_error)?;
-            let listing = listing.and_then(|f| match Listing::try_from(f) {
-                Ok(cat) => Some(cat),
-                Err(e) => {
-                    error!("{e}");
-                    None
-                }
-            });
+            select_listing(self, listing_id).await
+        }
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_listings_by_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        let Some((ref redis, ttl)) = self.redis else {
+            return Ok(select_listings_by_ids(self, listing_ids).await?.into_iter());
+        };
 
-            Ok(listing)
+        // each listing is read from the entry `get_listing_by_id` caches it in, so only the ones
+        // missing from the cache are selected
+        let keys: Vec<String> = listing_ids
+            .iter()
+            .map(|id| CacheKey::Listing { id }.to_string())
+            .collect();
+        let cached: Vec<Option<Option<Listing>>> =
+            redis_query::get_many(redis, self.local_cache.as_ref(), &keys).await;
+
+        let mut listings = Vec::with_capacity(listing_ids.len());
+        let mut missing = Vec::new();
+        for (id, cached) in listing_ids.iter().zip(cached) {
+            match cached {
+                Some(listing) => listings.extend(listing),
+                None => missing.push(*id),
+            }
+        }
+        if missing.is_empty() {
+            return Ok(listings.into_iter());
         }
+
+        let mut loaded: HashMap<Uuid, Listing> = select_listings_by_ids(self, &missing)
+            .await?
+            .into_iter()
+            .map(|listing| (listing.id, listing))
+            .collect();
+        // listings that were not found are cached too, as `get_listing_by_id` would
+        let entries = missing
+            .iter()
+            .map(|id| {
+                let listing = loaded.get(id).cloned();
+                let dependencies = vec![Dependency::Listing { id }.to_string()];
+                (CacheKey::Listing { id }.to_string(), listing, dependencies)
+            })
+            .collect();
+        redis_query::put_many(redis, ttl, self.local_cache.as_ref(), entries).await;
+
+        listings.extend(missing.iter().filter_map(|id| loaded.remove(id)));
+        Ok(listings.into_iter())
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_listing_owner(&self, listing_id: &Uuid) -> Result<Option<Uuid>, CoreError> {
+        let mut owners = self
+            .client
+            .query("SELECT VALUE in FROM sells WHERE out = type::thing($table, $id)")
+            .bind(("table", Collection::Listing))
+            .bind(("id", listing_id.to_string()))
+            .await
+            .map_err(map_db_error)?;
+
+        let owners: Vec<RecordId> = owners.take(0).map_err(map_db_error)?;
+
+        owners
+            .first()
+            .map(|owner| {
+                Uuid::parse_str(&create_string_from_id(owner))
+                    .map_err(|e| CoreError::Other(e.to_string()))
+            })
+            .transpose()
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_edges_by_listing_ids(
+        &self,
+        listing_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, ListingEdges>, CoreError> {
+        let ids: Vec<RecordId> = listing_ids
+            .iter()
+            .map(|id| create_thing_from_id(Collection::Listing, id))
+            .collect();
+
+        let mut edges = self
+            .client
+            .query(
+                "SELECT id, (<-sells<-user)[0] AS user, (<-sells.quantity)[0] AS quantity, \
+                 (->inCategory->category)[0] AS category, \
+                 (->withCondition->listing_condition)[0] AS condition \
+                 FROM type::table($table) WHERE id INSIDE $ids",
+            )
+            .bind(("table", Collection::Listing))
+            .bind(("ids", ids))
+            .await
+            .map_err(map_db_error)?;
+
+        let edges: Vec<DatabaseEntityListingEdges> = edges.take(0).map_err(map_db_error)?;
+
+        edges
+            .into_iter()
+            .filter_map(|edges| edges.into_edges().transpose())
+            .collect()
     }
 
     #[instrument(skip(self), err(Debug))]
@@ -222,7 +470,7 @@ impl QueryListings for Client {
         &self,
         user_id: &Uuid,
     ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
-        get_listings_by_field(self, "user_id", user_id).await
+        get_listings_by_field(self, "user", user_id).await
     }
 
     #[instrument(skip(self), err(Debug))]
@@ -230,60 +478,30 @@ impl QueryListings for Client {
         &self,
         category_id: &Uuid,
     ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
-        get_listings_by_field(self, "category_id", category_id).await
+        get_listings_by_field(self, "category", category_id).await
     }
 
     #[instrument(skip(self), err(Debug))]
     async fn get_listings_in_price_range(
         &self,
-        min: f64,
-        max: f64,
+        min: &Decimal,
+        max: &Decimal,
     ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
         let listings = if let Some((ref redis, ttl)) = self.redis {
-            let cache_key = CacheKey::AllListings;
-            let listings = redis_query::query::<Vec<Listing>>(cache_key, redis).await;
-
-            if let Some(listings) = listings {
-                listings
-            } else {
-                let mut listings = self
-                    .client
-                    .query( "SELECT * FROM {} where price >= type::decimal($min) and price <= type::decimal($max)").bind(("table", Collection::Listing))
-                    .bind(("min", min))
-                    .bind(("max", max))
-                    .await
-                    .map_err(map_db_error)?;
-
-                let listings: Vec<DatabaseEntityListing> =
-                    listings.take(0).map_err(map_db_error)?;
-
-                let listings = listings
-                    .into_iter()
-                    .map(Listing::try_from)
-                    .collect::<Result<Vec<Listing>, CoreError>>()?;
-
-                if let Err(e) = redis_query::update(cache_key, redis, &listings, ttl).await {
-                    error!(key = %cache_key, "[redis update]: {e}");
-                }
-
-                listings
-            }
+            let db = self.clone();
+            let (lower, upper) = (*min, *max);
+            redis_query::fetch(
+                redis,
+                ttl,
+                &self.flights,
+                self.local_cache.as_ref(),
+                CacheKey::PriceRange { min, max },
+                &[Dependency::Listings],
+                move || async move { select_listings_in_price_range(&db, &lower, &upper).await },
+            )
+            .await?
         } else {
-            let mut listings = self
-                .client
-                .query("SELECT * FROM type::table($table) where price >= type::decimal($min) and price <= type::decimal($max)")
-                .bind(("table", Collection::Listing))
-                .bind(("min", min))
-                .bind(("max", max))
-                .await
-                .map_err(map_db_error)?;
-
-            let listings: Vec<DatabaseEntityListing> = listings.take(0).map_err(map_db_error)?;
-
-            listings
-                .into_iter()
-                .map(Listing::try_from)
-                .collect::<Result<Vec<Listing>, CoreError>>()?
+            select_listings_in_price_range(self, min, max).await?
         };
 
         Ok(listings.into_iter())
@@ -293,11 +511,14 @@ impl QueryListings for Client {
     async fn search(
         &self,
         query: impl AsRef<str> + Send + std::fmt::Debug,
-    ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
+        status: ListingStatus,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Page<Listing>, CoreError> {
         if let Some(ref client) = self.search_client {
             let mut index = None;
             for _retries in 0..3 {
-                if let Ok(idx) = client.get_index("listings").await {
+                if let Ok(idx) = client.get_index(LISTINGS_INDEX).await {
                     index = Some(idx);
                     break;
                 }
@@ -305,13 +526,23 @@ impl QueryListings for Client {
             }
             match index {
                 Some(index) => {
-                    let query = SearchQuery::new(&index).with_query(query.as_ref()).build();
+                    // filtered by the index so the total and offset count the same listings
+                    let filter = format!("status = \"{status}\" AND deleted IS NULL");
+                    let query = SearchQuery::new(&index)
+                        .with_query(query.as_ref())
+                        .with_filter(&filter)
+                        .with_offset(offset)
+                        .with_limit(limit)
+                        .build();
 
                     let results: SearchResults<Listing> = index
                         .execute_query(&query)
                         .await
                         .map_err(|e| CoreError::Other(e.to_string()))?;
 
+                    let total = results
+                        .estimated_total_hits
+                        .unwrap_or(offset + results.hits.len());
                     let search_results: Vec<Listing> = results
                         .hits
                         .into_iter()
@@ -324,14 +555,17 @@ impl QueryListings for Client {
                             image_url: hit.result.image_url,
                             expires: hit.result.expires,
                             other_images: hit.result.other_images,
-                            published: hit.result.published,
+                            status: hit.result.status,
                             created: hit.result.created,
                             updated: hit.result.updated,
                             deleted: hit.result.deleted,
                         })
                         .collect();
 
-                    Ok(search_results.into_iter())
+                    Ok(Page {
+                        items: search_results,
+                        total,
+                    })
                 }
                 None => Err(CoreError::Other(
                     "items could not be indexed for search".into(),
@@ -344,29 +578,56 @@ impl QueryListings for Client {
         }
     }
 
+    async fn find_listings(
+        &self,
+        filter: &ListingFilter,
+        sort: ListingSort,
+        page: PageRequest,
+    ) -> Result<Page<Listing>, CoreError> {
+        if let Some((ref redis, ttl)) = self.redis {
+            let db = self.clone();
+            let owned_filter = filter.clone();
+            redis_query::fetch(
+                redis,
+                ttl,
+                &self.flights,
+                self.local_cache.as_ref(),
+                CacheKey::FindListings {
+                    filter,
+                    sort,
+                    page: &page,
+                },
+                // any listing may come to match the filter
+                &[Dependency::Listings],
+                move || async move { filter::find_listings(&db, &owned_filter, sort, page).await },
+            )
+            .await
+        } else {
+            filter::find_listings(self, filter, sort, page).await
+        }
+    }
+
     #[instrument(skip(self), err(Debug))]
     async fn get_listings_with_tags(
         &self,
         tags: &[&Uuid],
     ) -> Result<impl ExactSizeIterator<Item = Listing>, CoreError> {
-        let tags_vals: Vec<_> = tags
-            .iter()
-            .map(|f| create_thing_from_id(Collection::Tag, f))
-            .collect();
-
-        let mut query = self
-            .client
-            .query("SELECT * FROM type::table($table) WHERE tags CONTAINSANY type::array($values)")
-            .bind(("table", Collection::Listing))
-            .bind(("values", &tags_vals))
-            .await
-            .map_err(map_db_error)?;
-
-        let result: Vec<DatabaseEntityListing> = query.take(0).map_err(map_db_error)?;
-        let result = result
-            .into_iter()
-            .map(Listing::try_from)
-            .collect::<Result<Vec<Listing>, CoreError>>()?;
+        let ids: Vec<Uuid> = tags.iter().map(|id| **id).collect();
+        let result = if let Some((ref redis, ttl)) = self.redis {
+            let db = self.clone();
+            redis_query::fetch(
+                redis,
+                ttl,
+                &self.flights,
+                self.local_cache.as_ref(),
+                CacheKey::TagListing { tags },
+                &[Dependency::Listings],
+                move || async move { select_listings_with_tags(&db, &ids).await },
+            )
+            .await?
+        } else {
+            select_listings_with_tags(self, &ids).await?
+        };
 
         Ok(result.into_iter())
     }
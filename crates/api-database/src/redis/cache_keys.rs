@@ -1,26 +1,75 @@
 use std::fmt::Display;
 
-use api_core::reexports::uuid::Uuid;
+use api_core::{reexports::uuid::Uuid, ListingFilter, ListingSort, PageRequest};
 use redis::ToRedisArgs;
+use rust_decimal::Decimal;
+use sha2::{Digest, Sha256};
+
+/// Prefix of every cached query. Bumped whenever the encoding of cached values changes, so
+/// entries written by an older release are never decoded
+pub(crate) const CACHE_NAMESPACE: &str = "listings:v2";
 
 #[derive(Clone, Copy)]
 pub enum CacheKey<'a> {
     AllListings,
     AllTags,
     AllConditions,
-    UserListing { user_id: &'a Uuid },
-    Listing { id: &'a Uuid },
-    Tag { id: &'a Uuid },
+    UserListing {
+        user_id: &'a Uuid,
+    },
+    CategoryListing {
+        category_id: &'a Uuid,
+    },
+    ConditionListing {
+        condition_id: &'a Uuid,
+    },
+    /// Listings carrying any of the tags
+    TagListing {
+        tags: &'a [&'a Uuid],
+    },
+    PriceRange {
+        min: &'a Decimal,
+        max: &'a Decimal,
+    },
+    Listing {
+        id: &'a Uuid,
+    },
+    Tag {
+        id: &'a Uuid,
+    },
+    /// A page of the listings matching a filter
+    FindListings {
+        filter: &'a ListingFilter,
+        sort: ListingSort,
+        page: &'a PageRequest,
+    },
 }
 
 impl Display for CacheKey<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "listings:{}",
+            "{CACHE_NAMESPACE}:{}",
             match self {
                 CacheKey::AllListings => "all".to_string(),
                 CacheKey::UserListing { user_id } => format!("from_user={user_id}"),
+                CacheKey::CategoryListing { category_id } => {
+                    format!("in_category={category_id}")
+                }
+                CacheKey::ConditionListing { condition_id } => {
+                    format!("with_condition={condition_id}")
+                }
+                CacheKey::TagListing { tags } => {
+                    // the same tags in any order make the same query
+                    let mut tags = tags.to_vec();
+                    tags.sort_unstable();
+                    tags.dedup();
+                    let tags: Vec<_> = tags.iter().map(ToString::to_string).collect();
+                    format!("with_tags={}", tags.join(","))
+                }
+                CacheKey::PriceRange { min, max } => {
+                    format!("price={}..{}", min.normalize(), max.normalize())
+                }
                 CacheKey::Listing { id } => format!("id={id}"),
                 CacheKey::AllTags => {
                     "all_tags".to_string()
@@ -31,6 +80,11 @@ impl Display for CacheKey<'_> {
                 CacheKey::AllConditions => {
                     format!("conditions=all")
                 }
+                CacheKey::FindListings { filter, sort, page } => {
+                    // filters can be long, so they are hashed to keep the key short
+                    let query = format!("{filter:?}|{sort:?}|{page:?}");
+                    format!("find={}", hex::encode(Sha256::digest(query)))
+                }
             }
         )
     }
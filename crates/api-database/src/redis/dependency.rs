@@ -0,0 +1,106 @@
+use std::fmt::Display;
+
+use api_core::reexports::uuid::Uuid;
+use redis::ToRedisArgs;
+
+use super::{PoolLike, PooledConnectionLike, RedisPool};
+
+/// What a cached entry was built from. Each one is a set holding the keys of the entries that
+/// depend on it, so changing it clears all of them
+#[derive(Clone, Copy)]
+pub enum Dependency<'a> {
+    /// Any listing at all, for entries a listing can show up in whatever it is about
+    Listings,
+    Listing {
+        id: &'a Uuid,
+    },
+    User {
+        id: &'a Uuid,
+    },
+    Category {
+        id: &'a Uuid,
+    },
+    Condition {
+        id: &'a Uuid,
+    },
+}
+
+impl Display for Dependency<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "listings:depends_on:{}",
+            match self {
+                Dependency::Listings => "all".to_string(),
+                Dependency::Listing { id } => format!("listing={id}"),
+                Dependency::User { id } => format!("user={id}"),
+                Dependency::Category { id } => format!("category={id}"),
+                Dependency::Condition { id } => format!("condition={id}"),
+            }
+        )
+    }
+}
+
+impl ToRedisArgs for Dependency<'_> {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        out.write_arg(self.to_string().as_bytes())
+    }
+}
+
+/// Adds `cache_key` to the set of each of its dependencies. A set outlives none of the keys in
+/// it, as each key added pushes its expiry out to that of the key
+pub(crate) fn register(
+    pipe: &mut redis::Pipeline,
+    cache_key: &str,
+    dependencies: &[impl ToRedisArgs],
+    ttl: u64,
+) {
+    for dependency in dependencies {
+        pipe.sadd(dependency, cache_key)
+            .pexpire(dependency, ttl as i64);
+    }
+}
+
+/// Clears every cached entry depending on any of `dependencies`, returning the keys cleared.
+///
+/// The sets are read first, then every key found is deleted and taken out of its set in one
+/// pipeline. Only the keys that were found are taken out, so entries cached in the meantime are
+/// cleared by the next change. Each key gets a command of its own, as the keys of a cluster
+/// live on different nodes
+pub async fn invalidate(
+    redis: &RedisPool,
+    dependencies: &[Dependency<'_>],
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if dependencies.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut redis = redis.get().await?;
+
+    let mut lookup = redis::Pipeline::new();
+    for dependency in dependencies {
+        lookup.smembers(dependency);
+    }
+    let dependents: Vec<Vec<String>> = redis.query_async_pipeline(lookup).await?;
+    if dependents.iter().all(Vec::is_empty) {
+        return Ok(Vec::new());
+    }
+
+    let mut pipe = redis::Pipeline::new();
+    for (dependency, keys) in dependencies.iter().zip(&dependents) {
+        if keys.is_empty() {
+            continue;
+        }
+        for key in keys {
+            pipe.del(key);
+        }
+        pipe.srem(dependency, keys);
+    }
+
+    redis.query_async_pipeline::<()>(pipe).await?;
+
+    Ok(dependents.into_iter().flatten().collect())
+}
@@ -0,0 +1,115 @@
+use std::{
+    any::Any,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use futures_util::{pin_mut, StreamExt};
+use lru::LruCache;
+use tracing::{error, warn};
+
+use super::{cache_keys::CACHE_NAMESPACE, pubsub::ChangeListener, redis_query::LOCK_SUFFIX};
+
+/// How long to wait before listening for invalidated keys again once the subscription is lost
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+struct Slot {
+    value: Arc<dyn Any + Send + Sync>,
+    expires: Instant,
+}
+
+/// Entries read recently, kept in this process ahead of redis so hits skip the round trip and
+/// the decode. It holds up to a set number of entries, none of them for longer than its TTL
+#[derive(Clone)]
+pub struct LocalCache {
+    entries: Arc<Mutex<LruCache<String, Slot>>>,
+    ttl: Duration,
+}
+
+impl LocalCache {
+    pub fn new(capacity: NonZeroUsize, ttl: u64) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(LruCache::new(capacity))),
+            ttl: Duration::from_millis(ttl),
+        }
+    }
+
+    pub(crate) fn get<T: Clone + 'static>(&self, key: &str) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let (live, value) = match entries.get(key) {
+            Some(slot) => (slot.expires > Instant::now(), Arc::clone(&slot.value)),
+            None => return None,
+        };
+        if !live {
+            entries.pop(key);
+            return None;
+        }
+
+        value.downcast_ref::<T>().cloned()
+    }
+
+    /// Keeps `value` for as long as it stays fresh, up to the TTL of this cache
+    pub(crate) fn insert<T: Send + Sync + 'static>(
+        &self,
+        key: String,
+        value: T,
+        fresh_for: Duration,
+    ) {
+        let slot = Slot {
+            value: Arc::new(value),
+            expires: Instant::now() + fresh_for.min(self.ttl),
+        };
+
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .put(key, slot);
+    }
+
+    pub(crate) fn remove<K: AsRef<str>>(&self, keys: impl IntoIterator<Item = K>) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        for key in keys {
+            entries.pop(key.as_ref());
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+/// Whether a published key is that of a cached entry, rather than a lock, a set of dependencies
+/// or anything else kept in the same redis
+pub(crate) fn is_entry_key(key: &str) -> bool {
+    key.strip_prefix(CACHE_NAMESPACE)
+        .is_some_and(|rest| rest.starts_with(':'))
+        && !key.ends_with(LOCK_SUFFIX)
+}
+
+/// Drops the entries any replica clears from redis. Everything is dropped whenever the
+/// subscription is (re)opened, as keys may have been published while it was not
+pub(crate) async fn evict_invalidated(listener: ChangeListener, cache: LocalCache) {
+    loop {
+        match listener.invalidated_keys().await {
+            Ok(batches) => {
+                cache.clear();
+                pin_mut!(batches);
+                while let Some(keys) = batches.next().await {
+                    cache.remove(keys.iter().filter(|key| is_entry_key(key)));
+                }
+                warn!("no longer listening for invalidated keys");
+            }
+            Err(e) => {
+                error!("[invalidated keys subscribe]: {e}");
+            }
+        }
+
+        cache.clear();
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
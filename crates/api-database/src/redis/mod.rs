@@ -3,7 +3,11 @@
 mod cluster;
 
 pub(crate) mod cache_keys;
+pub(crate) mod dependency;
+pub(crate) mod local_cache;
+pub(crate) mod pubsub;
 pub(crate) mod redis_query;
+pub(crate) mod single_flight;
 
 use bb8::{Pool, RunError};
 use bb8_redis::RedisConnectionManager;
@@ -87,6 +91,15 @@ pub trait PooledConnectionLike {
             .await
     }
 
+    async fn publish<K: ToRedisArgs + Send, V: ToRedisArgs + Send, T: FromRedisValue>(
+        &mut self,
+        channel: K,
+        message: V,
+    ) -> RedisResult<T> {
+        self.query_async(redis::Cmd::publish(channel, message))
+            .await
+    }
+
     async fn rpush<K: ToRedisArgs + Send, V: ToRedisArgs + Send, T: FromRedisValue>(
         &mut self,
         key: K,
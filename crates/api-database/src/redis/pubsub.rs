@@ -0,0 +1,109 @@
+use api_core::ListingEvent;
+use futures_util::{Stream, StreamExt};
+use redis::{IntoConnectionInfo, RedisError};
+use tracing::error;
+
+use super::{PoolLike, PooledConnectionLike, RedisPool};
+
+/// The channel every replica publishes its listing changes on
+pub(crate) const LISTING_CHANGES: &str = "listings:changes";
+
+/// The channel every replica publishes the keys of the cached entries it cleared on
+pub(crate) const INVALIDATED_KEYS: &str = "listings:invalidated";
+
+/// Publishes a change to every replica listening on [`LISTING_CHANGES`]
+pub async fn publish(redis: &RedisPool, event: &ListingEvent) {
+    let bytes = match bincode::serialize(event) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("[change encode]: {e}");
+            return;
+        }
+    };
+
+    match redis.get().await {
+        Ok(mut redis) => {
+            if let Err(e) = redis.publish::<_, _, ()>(LISTING_CHANGES, bytes).await {
+                error!("[redis publish]: {e}");
+            }
+        }
+        Err(e) => {
+            error!("[redis pool]: {e}");
+        }
+    }
+}
+
+/// Publishes the keys of cleared entries to every replica listening on [`INVALIDATED_KEYS`], so
+/// they drop the copies they keep in process
+pub async fn publish_invalidated(redis: &RedisPool, keys: &[String]) {
+    if keys.is_empty() {
+        return;
+    }
+    let bytes = match bincode::serialize(keys) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("[invalidated keys encode]: {e}");
+            return;
+        }
+    };
+
+    match redis.get().await {
+        Ok(mut redis) => {
+            if let Err(e) = redis.publish::<_, _, ()>(INVALIDATED_KEYS, bytes).await {
+                error!("[redis publish]: {e}");
+            }
+        }
+        Err(e) => {
+            error!("[redis pool]: {e}");
+        }
+    }
+}
+
+/// Listens for the changes published by any replica.
+///
+/// Subscriptions hold on to their connection, so they are opened outside of the pool. In a
+/// cluster every node forwards what is published to its subscribers, so a connection to any one
+/// of them is enough
+#[derive(Clone, Debug)]
+pub struct ChangeListener {
+    client: redis::Client,
+}
+
+impl ChangeListener {
+    pub fn new<T: IntoConnectionInfo>(info: T) -> Result<Self, RedisError> {
+        Ok(Self {
+            client: redis::Client::open(info)?,
+        })
+    }
+
+    pub async fn subscribe(&self) -> Result<impl Stream<Item = ListingEvent>, RedisError> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(LISTING_CHANGES).await?;
+
+        Ok(pubsub.into_on_message().filter_map(|message| async move {
+            match bincode::deserialize::<ListingEvent>(message.get_payload_bytes()) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    error!("[change decode]: {e}");
+                    None
+                }
+            }
+        }))
+    }
+
+    /// Listens for the keys of the entries any replica cleared, one batch per change
+    pub async fn invalidated_keys(&self) -> Result<impl Stream<Item = Vec<String>>, RedisError> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(INVALIDATED_KEYS).await?;
+
+        Ok(pubsub.into_on_message().filter_map(|message| async move {
+            match bincode::deserialize::<Vec<String>>(message.get_payload_bytes()) {
+                Ok(keys) => Some(keys),
+                Err(e) => {
+                    error!("[invalidated keys decode]: {e}");
+                    None
+                }
+            }
+        }))
+    }
+}
@@ -1,51 +1,379 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use api_core::{api::CoreError, reexports::uuid::Uuid};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::time::Instant;
 use tracing::error;
 
-use super::{cache_keys::CacheKey, PoolLike, PooledConnectionLike, RedisPool};
+use super::{
+    cache_keys::CacheKey,
+    dependency::{self, Dependency},
+    local_cache::LocalCache,
+    single_flight::Flights,
+    PoolLike, PooledConnectionLike, RedisPool,
+};
+
+/// Appended to the key of an entry to name the lock on loading it
+pub(crate) const LOCK_SUFFIX: &str = ":lock";
+
+/// How long a replica has to load an entry before another one may take over
+const LOCK_LEASE: Duration = Duration::from_secs(5);
+
+/// How often a replica waiting on another one to load an entry checks whether it has
+const LOCK_POLL: Duration = Duration::from_millis(50);
+
+/// Releases a lock only if it is still held by whoever took it
+const RELEASE_LOCK: &str = r#"if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0"#;
+
+/// An entry as it is cached. Entries are kept for as long again once they go stale, so they can
+/// be served while they are refreshed
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    /// Unix time in milliseconds. It goes first, so it can be read without knowing what the
+    /// entry holds
+    fresh_until: i64,
+    data: T,
+}
+
+/// Whether a cached entry is fresh, whatever it holds
+fn is_fresh(bytes: &[u8]) -> bool {
+    bincode::deserialize::<i64>(bytes).is_ok_and(|fresh_until| fresh_until > now_millis())
+}
+
+enum Lookup<T> {
+    Fresh(Entry<T>),
+    Stale(T),
+    Missing,
+}
+
+fn now_millis() -> i64 {
+    (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+/// Keeps an entry in this process for as long as it stays fresh
+fn keep_local<T>(local: Option<&LocalCache>, key: &str, entry: &Entry<T>)
+where
+    T: Clone + Send + Sync + 'static,
+{
+    if let Some(local) = local {
+        let fresh_for = u64::try_from(entry.fresh_until - now_millis()).unwrap_or_default();
+        local.insert(
+            key.to_string(),
+            entry.data.clone(),
+            Duration::from_millis(fresh_for),
+        );
+    }
+}
+
+async fn lookup<T: DeserializeOwned>(redis: &RedisPool, key: &str) -> Lookup<T> {
+    let bytes = match redis.get().await {
+        Ok(mut redis) => match redis.get::<_, Vec<u8>>(key).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("[redis]: {e}");
+                return Lookup::Missing;
+            }
+        },
+        Err(e) => {
+            error!("[redis pool]: {e}");
+            return Lookup::Missing;
+        }
+    };
+
+    if bytes.is_empty() {
+        return Lookup::Missing;
+    }
+
+    match bincode::deserialize::<Entry<T>>(&bytes[..]) {
+        Ok(entry) if entry.fresh_until > now_millis() => Lookup::Fresh(entry),
+        Ok(entry) => Lookup::Stale(entry.data),
+        Err(decode_err) => {
+            error!(key, "[cache decode]: {decode_err}");
+            Lookup::Missing
+        }
+    }
+}
 
-pub async fn query<T: serde::de::DeserializeOwned>(
+/// Reads `cache_key` through the cache, loading it with `load` when it is missing or stale.
+///
+/// Entries are looked for in `local` first, when there is a cache in this process. Concurrent
+/// misses in this process share a single load, and a lock in redis has the other replicas wait
+/// on it rather than load the same entry themselves. Stale entries are served as they are while
+/// a load in the background refreshes them
+pub async fn fetch<T, F, Fut>(
+    redis: &RedisPool,
+    ttl: u64,
+    flights: &Flights,
+    local: Option<&LocalCache>,
     cache_key: CacheKey<'_>,
+    dependencies: &[Dependency<'_>],
+    load: F,
+) -> Result<T, CoreError>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, CoreError>> + Send + 'static,
+{
+    let key = cache_key.to_string();
+
+    if let Some(data) = local.and_then(|local| local.get::<T>(&key)) {
+        return Ok(data);
+    }
+
+    let stale = match lookup::<T>(redis, &key).await {
+        Lookup::Fresh(entry) => {
+            keep_local(local, &key, &entry);
+            return Ok(entry.data);
+        }
+        Lookup::Stale(data) => Some(data),
+        Lookup::Missing => None,
+    };
+
+    let dependencies = dependencies.iter().map(ToString::to_string).collect();
+    let flight = flights.join(
+        key.clone(),
+        refresh(redis.clone(), key.clone(), dependencies, ttl, load),
+    );
+
+    match stale {
+        // the load runs on its own, so nothing needs to wait on it
+        Some(data) => Ok(data),
+        None => {
+            let bytes = flight.await?;
+            let entry = bincode::deserialize::<Entry<T>>(&bytes)
+                .map_err(|e| CoreError::Other(e.to_string()))?;
+
+            keep_local(local, &key, &entry);
+            Ok(entry.data)
+        }
+    }
+}
+
+/// Reads the entries of `keys` that are cached and fresh, from `local` first and then from redis
+/// in a single round trip for the rest. Entries that are missing or stale come back as `None`,
+/// in the same order as their keys
+pub async fn get_many<T>(
     redis: &RedisPool,
-) -> Option<T> {
-    match redis.get().await {
-        Ok(mut redis) => match redis.get::<_, Vec<u8>>(cache_key).await {
+    local: Option<&LocalCache>,
+    keys: &[String],
+) -> Vec<Option<T>>
+where
+    T: DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    let mut found: Vec<Option<T>> = keys
+        .iter()
+        .map(|key| local.and_then(|local| local.get::<T>(key)))
+        .collect();
+
+    let missing: Vec<&String> = keys
+        .iter()
+        .zip(&found)
+        .filter(|(_, data)| data.is_none())
+        .map(|(key, _)| key)
+        .collect();
+    if missing.is_empty() {
+        return found;
+    }
+
+    let mut cmd = redis::cmd("MGET");
+    cmd.arg(&missing);
+    let cached = match redis.get().await {
+        Ok(mut redis) => match redis.query_async::<Vec<Option<Vec<u8>>>>(cmd).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                error!("[redis]: {e}");
+                return found;
+            }
+        },
+        Err(e) => {
+            error!("[redis pool]: {e}");
+            return found;
+        }
+    };
+
+    let slots = found.iter_mut().filter(|data| data.is_none());
+    for ((slot, key), bytes) in slots.zip(missing).zip(cached) {
+        let Some(bytes) = bytes else {
+            continue;
+        };
+        match bincode::deserialize::<Entry<T>>(&bytes) {
+            Ok(entry) if entry.fresh_until > now_millis() => {
+                keep_local(local, key, &entry);
+                *slot = Some(entry.data);
+            }
+            Ok(_) => {}
+            Err(decode_err) => error!(key, "[cache decode]: {decode_err}"),
+        }
+    }
+
+    found
+}
+
+/// Caches entries loaded together in a single round trip, each with the keys of its own
+/// dependencies. Unlike [`fetch`], no lock is taken, so replicas loading the same entries at
+/// once each load them
+pub async fn put_many<T>(
+    redis: &RedisPool,
+    ttl: u64,
+    local: Option<&LocalCache>,
+    entries: Vec<(String, T, Vec<String>)>,
+) where
+    T: Serialize + Clone + Send + Sync + 'static,
+{
+    if entries.is_empty() {
+        return;
+    }
+    // kept for as long again as the entry is fresh, to be served while it is refreshed
+    let keep_for = ttl.saturating_mul(2);
+    let fresh_until = now_millis().saturating_add(ttl as i64);
+
+    let mut pipe = redis::Pipeline::new();
+    for (key, data, dependencies) in entries {
+        let entry = Entry { fresh_until, data };
+        match bincode::serialize(&entry) {
             Ok(bytes) => {
-                if bytes.is_empty() {
-                    None
-                } else {
-                    match bincode::deserialize::<T>(&bytes[..]) {
-                        Ok(value) => Some(value),
-                        Err(decode_err) => {
-                            error!(key = %cache_key, "[cache decode]: {decode_err}");
-                            None
-                        }
-                    }
-                }
+                pipe.pset_ex(&key, bytes, keep_for);
+                dependency::register(&mut pipe, &key, &dependencies, keep_for);
+                keep_local(local, &key, &entry);
+            }
+            Err(e) => error!(key, "[cache encode]: {e}"),
+        }
+    }
+
+    match redis.get().await {
+        Ok(mut redis) => {
+            if let Err(e) = redis.query_async_pipeline::<()>(pipe).await {
+                error!("[cache update]: {e}");
+            }
+        }
+        Err(e) => {
+            error!("[redis pool]: {e}");
+        }
+    }
+}
+
+/// Loads an entry and caches it, unless another replica holds the lock on it. That replica is
+/// waited on for as long as its lease lasts, after which the entry is loaded here all the same
+async fn refresh<T, F, Fut>(
+    redis: RedisPool,
+    key: String,
+    dependencies: Vec<String>,
+    ttl: u64,
+    load: F,
+) -> Result<Arc<[u8]>, CoreError>
+where
+    T: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, CoreError>>,
+{
+    let lock = format!("{key}{LOCK_SUFFIX}");
+    let token = Uuid::now_v7().to_string();
+
+    let locked = lock_entry(&redis, &lock, &token).await;
+    if !locked {
+        let deadline = Instant::now() + LOCK_LEASE;
+        while Instant::now() < deadline {
+            tokio::time::sleep(LOCK_POLL).await;
+            if let Some(bytes) = fresh_bytes(&redis, &key).await {
+                return Ok(bytes);
             }
+        }
+    }
+
+    let bytes = load().await.and_then(|data| {
+        let entry = Entry {
+            fresh_until: now_millis().saturating_add(ttl as i64),
+            data,
+        };
+        bincode::serialize(&entry)
+            .map(Arc::<[u8]>::from)
+            .map_err(|e| CoreError::Other(e.to_string()))
+    });
+
+    if let Ok(ref bytes) = bytes {
+        if let Err(e) = update(&redis, &key, bytes, ttl, &dependencies).await {
+            error!(key, "[cache update]: {e}");
+        }
+    }
+    // released even when the load failed, so the other replicas need not wait out the lease
+    if locked {
+        unlock_entry(&redis, &lock, &token).await;
+    }
+
+    bytes
+}
+
+async fn fresh_bytes(redis: &RedisPool, key: &str) -> Option<Arc<[u8]>> {
+    let mut redis = redis.get().await.ok()?;
+    let bytes = redis.get::<_, Vec<u8>>(key).await.ok()?;
+
+    is_fresh(&bytes).then(|| bytes.into())
+}
+
+/// Takes the lock on loading an entry. When redis cannot tell, the entry is loaded as if the
+/// lock was taken
+async fn lock_entry(redis: &RedisPool, lock: &str, token: &str) -> bool {
+    let mut cmd = redis::cmd("SET");
+    cmd.arg(lock)
+        .arg(token)
+        .arg("NX")
+        .arg("PX")
+        .arg(LOCK_LEASE.as_millis() as u64);
+
+    match redis.get().await {
+        Ok(mut redis) => match redis.query_async::<Option<String>>(cmd).await {
+            Ok(set) => set.is_some(),
             Err(e) => {
-                error!("[redis]: {e}");
-                None
+                error!("[redis lock]: {e}");
+                true
             }
         },
         Err(e) => {
             error!("[redis pool]: {e}");
-            None
+            true
         }
     }
 }
 
-pub async fn update<T: serde::Serialize>(
-    cache_key: CacheKey<'_>,
+async fn unlock_entry(redis: &RedisPool, lock: &str, token: &str) {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(RELEASE_LOCK).arg(1).arg(lock).arg(token);
+
+    match redis.get().await {
+        Ok(mut redis) => {
+            if let Err(e) = redis.query_async::<()>(cmd).await {
+                error!("[redis unlock]: {e}");
+            }
+        }
+        Err(e) => {
+            error!("[redis pool]: {e}");
+        }
+    }
+}
+
+/// Caches an entry, registering its key with each of its `dependencies` so it is cleared when
+/// any of them change. Entries without dependencies only ever expire
+async fn update(
     redis: &RedisPool,
-    data: T,
+    key: &str,
+    bytes: &[u8],
     ttl: u64,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let bytes = bincode::serialize(&data)?;
+    dependencies: &[String],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // kept for as long again as the entry is fresh, to be served while it is refreshed
+    let keep_for = ttl.saturating_mul(2);
 
     let mut redis = redis.get().await?;
 
-    if let Err(e) = redis.pset_ex::<_, _, ()>(cache_key, bytes, ttl).await {
-        error!(key = %cache_key,"[cache update]: {e}");
-    }
+    let mut pipe = redis::Pipeline::new();
+    pipe.pset_ex(key, bytes, keep_for);
+    dependency::register(&mut pipe, key, dependencies, keep_for);
+
+    redis.query_async_pipeline::<()>(pipe).await?;
 
     Ok(())
 }
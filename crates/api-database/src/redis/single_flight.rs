@@ -0,0 +1,57 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use api_core::api::CoreError;
+use futures_util::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+
+/// A load of a cached entry, resolving to the entry as it was cached
+pub(crate) type Flight = Shared<BoxFuture<'static, Result<Arc<[u8]>, CoreError>>>;
+
+/// The loads running in this process by cache key, so concurrent misses of the same key share
+/// a single load
+#[derive(Clone, Default)]
+pub struct Flights {
+    running: Arc<Mutex<HashMap<String, Flight>>>,
+}
+
+impl Flights {
+    /// Joins the load of `key` that is already running, or spawns `load` as it. The load runs
+    /// to completion on its own, whether or not anyone is still waiting on it
+    pub(crate) fn join<L>(&self, key: String, load: L) -> Flight
+    where
+        L: Future<Output = Result<Arc<[u8]>, CoreError>> + Send + 'static,
+    {
+        let mut running = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(flight) = running.get(&key) {
+            return flight.clone();
+        }
+
+        let flights = self.clone();
+        let landed = key.clone();
+        let task = tokio::spawn(async move {
+            let entry = load.await;
+            // misses from here on start a load of their own
+            flights
+                .running
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&landed);
+            entry
+        });
+        let flight = async move {
+            task.await
+                .unwrap_or_else(|e| Err(CoreError::Other(e.to_string())))
+        }
+        .boxed()
+        .shared();
+
+        running.insert(key, flight.clone());
+        flight
+    }
+}
@@ -0,0 +1,85 @@
+use api_core::{
+    api::CoreError, reexports::uuid::Uuid, ListingFilter, ListingSort, ListingStatus, PageRequest,
+};
+use rust_decimal::Decimal;
+
+use crate::{collections::Collection, redis::cache_keys::CacheKey};
+
+#[test]
+fn tag_listing_keys_ignore_order() {
+    let (a, b) = (Uuid::now_v7(), Uuid::now_v7());
+
+    let key = CacheKey::TagListing { tags: &[&a, &b] }.to_string();
+
+    assert_eq!(key, CacheKey::TagListing { tags: &[&b, &a] }.to_string());
+    assert_eq!(
+        key,
+        CacheKey::TagListing {
+            tags: &[&b, &a, &b]
+        }
+        .to_string()
+    );
+    assert_ne!(key, CacheKey::TagListing { tags: &[&a] }.to_string());
+}
+
+#[test]
+fn price_range_keys() {
+    let (min, max) = (Decimal::new(1000, 2), Decimal::new(25, 0));
+
+    let key = CacheKey::PriceRange {
+        min: &min,
+        max: &max,
+    };
+
+    assert_eq!(key.to_string(), "listings:v2:price=10..25");
+}
+
+#[test]
+fn find_listings_keys() {
+    let filter = ListingFilter {
+        status: Some(ListingStatus::Active),
+        ..Default::default()
+    };
+    let key = |filter: &ListingFilter, sort: ListingSort, page: &PageRequest| {
+        CacheKey::FindListings { filter, sort, page }.to_string()
+    };
+    let first = key(&filter, ListingSort::PriceAsc, &PageRequest::first(10));
+
+    assert!(first.starts_with("listings:v2:find="));
+    assert_eq!(
+        first,
+        key(
+            &filter.clone(),
+            ListingSort::PriceAsc,
+            &PageRequest::first(10)
+        )
+    );
+    assert_ne!(
+        first,
+        key(&filter, ListingSort::PriceDesc, &PageRequest::first(10))
+    );
+    assert_ne!(
+        first,
+        key(&filter, ListingSort::PriceAsc, &PageRequest::last(10))
+    );
+    assert_ne!(
+        first,
+        key(
+            &ListingFilter::default(),
+            ListingSort::PriceAsc,
+            &PageRequest::first(10)
+        )
+    );
+}
+
+#[test]
+fn collection_from_str() {
+    assert!(matches!(
+        Collection::try_from("category"),
+        Ok(Collection::Category)
+    ));
+    assert!(matches!(
+        Collection::try_from("category_id"),
+        Err(CoreError::Database(_))
+    ));
+}
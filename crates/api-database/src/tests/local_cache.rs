@@ -0,0 +1,43 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use api_core::reexports::uuid::Uuid;
+
+use crate::redis::{
+    cache_keys::CacheKey,
+    dependency::Dependency,
+    local_cache::{is_entry_key, LocalCache},
+};
+
+#[test]
+fn local_cache_entries() {
+    let cache = LocalCache::new(NonZeroUsize::new(2).unwrap(), 60_000);
+    let fresh_for = Duration::from_secs(60);
+
+    cache.insert(String::from("a"), 1, fresh_for);
+    cache.insert(String::from("b"), 2, fresh_for);
+    assert_eq!(cache.get::<i32>("a"), Some(1));
+    assert_eq!(cache.get::<String>("a"), None);
+
+    // "b" was used least recently, so it makes way
+    cache.insert(String::from("c"), 3, fresh_for);
+    assert_eq!(cache.get::<i32>("b"), None);
+    assert_eq!(cache.get::<i32>("c"), Some(3));
+
+    cache.remove(["a"]);
+    assert_eq!(cache.get::<i32>("a"), None);
+
+    // entries go once they are no longer fresh, however long the cache keeps them for
+    cache.insert(String::from("d"), 4, Duration::ZERO);
+    assert_eq!(cache.get::<i32>("d"), None);
+}
+
+#[test]
+fn local_cache_evicts_entry_keys() {
+    let id = Uuid::now_v7();
+    let entry = CacheKey::Listing { id: &id }.to_string();
+
+    assert!(is_entry_key(&entry));
+    assert!(!is_entry_key(&format!("{entry}:lock")));
+    assert!(!is_entry_key(&Dependency::Listing { id: &id }.to_string()));
+    assert!(!is_entry_key("listings:v1:all"));
+}
@@ -1,7 +1,10 @@
+mod cache_keys;
 mod external_mutation;
+mod local_cache;
 mod mutation;
 mod query;
 mod redis;
+mod webhook;
 
 use crate::Client;
 use anyhow::Result;
@@ -50,7 +53,9 @@ async fn create_client(
     }
 
     if with_search {
-        client.with_meilisearch(&meilisearch_host, meilisearch_api_key.as_deref());
+        client
+            .with_meilisearch(&meilisearch_host, meilisearch_api_key.as_deref())
+            .await?;
     }
 
     Ok(client)
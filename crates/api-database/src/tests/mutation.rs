@@ -9,7 +9,7 @@ use anyhow::Result;
 use api_core::{
     api::{MutateListings, QueryListings},
     reexports::uuid::Uuid,
-    Listing,
+    Listing, ListingStatus,
 };
 use fake::{
     faker::{internet::en::Username, lorem::en::Words},
@@ -32,7 +32,7 @@ fn create_listing_item() -> Listing {
         category_id: Uuid::now_v7(),
         image_url: String::from("https://dummyimage.com/420x260"),
         other_images: vec![],
-        published: true,
+        status: ListingStatus::Active,
         location_id: Uuid::now_v7(),
         created: OffsetDateTime::now_utc(),
         deleted: None,
@@ -113,3 +113,11 @@ async fn create_listing() -> Result<()> {
     client.delete_listing(&input.id, &user_id).await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn dispatch_empty_outbox() -> Result<()> {
+    let client = create_client(Some("test-mutation-outbox"), false, false).await?;
+
+    assert_eq!(client.dispatch_outbox(10).await?, 0);
+    Ok(())
+}
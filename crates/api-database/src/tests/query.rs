@@ -4,7 +4,7 @@ use crate::{
     Client,
 };
 use anyhow::Result;
-use api_core::{api::QueryListings, reexports::uuid::Uuid, Listing};
+use api_core::{api::QueryListings, reexports::uuid::Uuid, Listing, ListingStatus};
 
 async fn check_listings_by_id(client: Client, id: &Uuid, expected_result: bool) -> Result<()> {
     match client.get_listing_by_id(id).await {
@@ -68,7 +68,9 @@ async fn query_with_meilisearch() -> Result<()> {
     }
 
     let _results: Vec<_> = client.get_listings().await?.collect();
-    let res = client.search("some thing").await;
+    let res = client
+        .search("some thing", ListingStatus::Active, 0, 10)
+        .await;
     assert!(res.is_ok());
 
     Ok(())
@@ -80,3 +82,100 @@ async fn query_all() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn compile_listing_filter() {
+    use crate::query::filter::compile;
+    use api_core::{ListingFilter, ListingKey, ListingSort, PageRequest, SortValue};
+
+    let compiled = compile(
+        &ListingFilter::default(),
+        ListingSort::default(),
+        &PageRequest::first(10),
+    );
+    assert_eq!(
+        compiled.sql,
+        "SELECT * FROM type::table($table) WHERE !deleted \
+         ORDER BY created DESC, id ASC LIMIT $limit"
+    );
+    assert_eq!(
+        compiled.count_sql,
+        "SELECT count() AS total FROM type::table($table) WHERE !deleted GROUP ALL"
+    );
+    assert!(compiled.bindings.is_empty());
+
+    let category = Uuid::now_v7();
+    let filter = ListingFilter {
+        category_id: Some(category),
+        max_price: Some(200.into()),
+        negotiable: Some(true),
+        ..Default::default()
+    };
+    let compiled = compile(&filter, ListingSort::PriceAsc, &PageRequest::first(10));
+    assert_eq!(
+        compiled.sql,
+        "SELECT * FROM type::table($table) WHERE !deleted \
+         AND ->inCategory->category CONTAINS type::thing('category', $category_id) \
+         AND price <= type::decimal($max_price) \
+         AND negotiable = type::bool($negotiable) \
+         ORDER BY price ASC, id ASC LIMIT $limit"
+    );
+    assert_eq!(
+        compiled.bindings,
+        vec![
+            ("category_id", category.to_string()),
+            ("max_price", String::from("200")),
+            ("negotiable", String::from("true")),
+        ]
+    );
+
+    // the page bounds are left out of the count
+    let id = Uuid::now_v7();
+    let key = ListingKey {
+        value: SortValue::Price(150.into()),
+        id,
+    };
+    let compiled = compile(
+        &ListingFilter::default(),
+        ListingSort::PriceDesc,
+        &PageRequest::last(5).with_before(Some(key)),
+    );
+    assert_eq!(
+        compiled.sql,
+        "SELECT * FROM type::table($table) WHERE !deleted \
+         AND (price > type::decimal($before_value) OR (price = type::decimal($before_value) \
+         AND id < type::thing($table, $before_id))) \
+         ORDER BY price ASC, id DESC LIMIT $limit"
+    );
+    assert_eq!(
+        compiled.count_sql,
+        "SELECT count() AS total FROM type::table($table) WHERE !deleted GROUP ALL"
+    );
+    assert_eq!(
+        compiled.bindings,
+        vec![
+            ("before_value", String::from("150")),
+            ("before_id", id.to_string()),
+        ]
+    );
+
+    let key = ListingKey {
+        value: SortValue::Expires(None),
+        id,
+    };
+    let compiled = compile(
+        &ListingFilter::default(),
+        ListingSort::ExpiresAsc,
+        &PageRequest::first(5).with_after(Some(key)),
+    );
+    assert_eq!(
+        compiled.sql,
+        "SELECT *, (expires ?? d'9999-12-31T23:59:59Z') AS expires_order \
+         FROM type::table($table) WHERE !deleted \
+         AND ((expires ?? d'9999-12-31T23:59:59Z') > d'9999-12-31T23:59:59Z' \
+         OR ((expires ?? d'9999-12-31T23:59:59Z') = d'9999-12-31T23:59:59Z' \
+         AND id > type::thing($table, $after_id))) \
+         ORDER BY expires_order ASC, id ASC LIMIT $limit"
+    );
+    assert_eq!(compiled.bindings, vec![("after_id", id.to_string())]);
+}
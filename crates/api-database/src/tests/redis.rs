@@ -9,10 +9,14 @@ async fn get_pool(redis_dsn: &str, max_pool_size: u16, is_cluster: bool) -> Redi
     }
 }
 
-async fn client() -> RedisPool {
+fn redis_dsn() -> String {
     dotenvy::dotenv().ok();
 
-    let redis_dsn = std::env::var("TEST_REDIS_HOST").unwrap_or("redis://localhost:6379".to_owned());
+    std::env::var("TEST_REDIS_HOST").unwrap_or("redis://localhost:6379".to_owned())
+}
+
+async fn client() -> RedisPool {
+    let redis_dsn = redis_dsn();
 
     dbg!(&redis_dsn);
 
@@ -112,3 +116,213 @@ async fn redis_sets_check() -> Result<()> {
     let _ = pool.del::<&str, ()>(key).await;
     Ok(())
 }
+
+#[tokio::test]
+async fn redis_publish_changes() -> Result<()> {
+    use api_core::{reexports::uuid::Uuid, Listing, ListingAction, ListingEvent, ListingStatus};
+    use futures_util::{pin_mut, StreamExt};
+    use time::OffsetDateTime;
+
+    use crate::redis::pubsub::{publish, ChangeListener};
+
+    let listener = ChangeListener::new(redis_dsn())?;
+    let changes = listener.subscribe().await?;
+    pin_mut!(changes);
+
+    let pool = client().await;
+    let listing = Listing {
+        id: Uuid::nil(),
+        title: String::from("Title"),
+        description: String::from("Description"),
+        price: 10.into(),
+        image_url: String::from("https://dummyimage.com/420x260"),
+        other_images: vec![],
+        status: ListingStatus::Active,
+        negotiable: false,
+        created: OffsetDateTime::now_utc(),
+        expires: None,
+        updated: OffsetDateTime::now_utc(),
+        deleted: None,
+    };
+    let event = ListingEvent::new(ListingAction::Created, listing);
+    publish(&pool, &event).await;
+
+    assert_eq!(changes.next().await, Some(event));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn redis_publish_invalidated_keys() -> Result<()> {
+    use futures_util::{pin_mut, StreamExt};
+
+    use crate::redis::pubsub::{publish_invalidated, ChangeListener};
+
+    let listener = ChangeListener::new(redis_dsn())?;
+    let invalidated = listener.invalidated_keys().await?;
+    pin_mut!(invalidated);
+
+    let pool = client().await;
+    let keys = vec![
+        String::from("listings:v2:all"),
+        String::from("listings:v2:id=0"),
+    ];
+    publish_invalidated(&pool, &[]).await;
+    publish_invalidated(&pool, &keys).await;
+
+    // nothing is published for changes that cleared nothing
+    assert_eq!(invalidated.next().await, Some(keys));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn redis_invalidate_dependents() -> Result<()> {
+    use api_core::reexports::uuid::Uuid;
+
+    use crate::redis::{
+        cache_keys::CacheKey,
+        dependency::{invalidate, Dependency},
+        redis_query::fetch,
+        single_flight::Flights,
+    };
+
+    let pool = client().await;
+    let flights = Flights::default();
+    let (listing, user) = (Uuid::now_v7(), Uuid::now_v7());
+
+    let by_id = CacheKey::Listing { id: &listing };
+    let by_user = CacheKey::UserListing { user_id: &user };
+    let listing_deps = [Dependency::Listing { id: &listing }];
+    let user_deps = [Dependency::User { id: &user }];
+
+    assert_eq!(
+        fetch(
+            &pool,
+            5000,
+            &flights,
+            None,
+            by_id,
+            &listing_deps,
+            || async { Ok(1) }
+        )
+        .await?,
+        1
+    );
+    assert_eq!(
+        fetch(&pool, 5000, &flights, None, by_user, &user_deps, || async {
+            Ok(2)
+        })
+        .await?,
+        2
+    );
+
+    invalidate(&pool, &listing_deps).await.unwrap();
+
+    assert_eq!(
+        fetch(
+            &pool,
+            5000,
+            &flights,
+            None,
+            by_id,
+            &listing_deps,
+            || async { Ok(3) }
+        )
+        .await?,
+        3
+    );
+    assert_eq!(
+        fetch(&pool, 5000, &flights, None, by_user, &user_deps, || async {
+            Ok(4)
+        })
+        .await?,
+        2
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn redis_fetch_loads_once() -> Result<()> {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use api_core::reexports::uuid::Uuid;
+    use futures_util::future::join_all;
+
+    use crate::redis::{cache_keys::CacheKey, redis_query::fetch, single_flight::Flights};
+
+    let pool = client().await;
+    let flights = Flights::default();
+    let loads = Arc::new(AtomicUsize::new(0));
+    let id = Uuid::now_v7();
+
+    let fetches = (0..10).map(|_| {
+        let loads = Arc::clone(&loads);
+        fetch(
+            &pool,
+            5000,
+            &flights,
+            None,
+            CacheKey::Listing { id: &id },
+            &[],
+            move || async move {
+                loads.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                Ok(id)
+            },
+        )
+    });
+
+    for fetched in join_all(fetches).await {
+        assert_eq!(fetched?, id);
+    }
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn redis_get_many_reads_cached() -> Result<()> {
+    use std::num::NonZeroUsize;
+
+    use api_core::reexports::uuid::Uuid;
+
+    use crate::redis::{
+        cache_keys::CacheKey,
+        local_cache::LocalCache,
+        redis_query::{get_many, put_many},
+    };
+
+    let pool = client().await;
+    let ids = [Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7()];
+    let keys: Vec<String> = ids
+        .iter()
+        .map(|id| CacheKey::Listing { id }.to_string())
+        .collect();
+
+    put_many(
+        &pool,
+        5000,
+        None,
+        vec![
+            (keys[0].clone(), Some(ids[0]), Vec::new()),
+            (keys[1].clone(), None, Vec::new()),
+        ],
+    )
+    .await;
+
+    let cached = get_many::<Option<Uuid>>(&pool, None, &keys).await;
+    assert_eq!(cached, [Some(Some(ids[0])), Some(None), None]);
+
+    // read from redis once, then from the process
+    let local = LocalCache::new(NonZeroUsize::new(10).unwrap(), 5000);
+    get_many::<Option<Uuid>>(&pool, Some(&local), &keys).await;
+    assert_eq!(local.get::<Option<Uuid>>(&keys[0]), Some(Some(ids[0])));
+    assert_eq!(local.get::<Option<Uuid>>(&keys[2]), None);
+
+    Ok(())
+}
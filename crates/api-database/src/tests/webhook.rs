@@ -0,0 +1,79 @@
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    str::FromStr,
+    thread,
+};
+
+use reqwest::dns::{Name, Resolve};
+
+use crate::webhook::{
+    client::{check_host, http_client, PublicResolver},
+    sign,
+};
+
+#[test]
+fn webhook_signature() {
+    let signature = sign("a-secret-of-some-length", 1700000000, r#"{"id":"0"}"#);
+
+    assert_eq!(
+        signature,
+        "sha256=4b1c5aaa86e1ee8033fcd940e45470c42d11dbcd73878a893f5aaf403b2e2bc0"
+    );
+    assert_ne!(
+        signature,
+        sign("a-secret-of-some-length", 1700000001, r#"{"id":"0"}"#)
+    );
+}
+
+#[test]
+fn webhook_internal_hosts() {
+    for url in [
+        "https://127.0.0.1/hooks",
+        "https://2130706433/hooks",
+        "https://127.1/hooks",
+        "https://0x7f.0.0.1/hooks",
+        "https://169.254.169.254/latest",
+        "https://[::1]/hooks",
+        "https://[::ffff:10.0.0.1]/hooks",
+    ] {
+        assert!(check_host(url).is_err(), "{url} was accepted");
+    }
+
+    assert!(check_host("https://example.com/hooks").is_ok());
+}
+
+#[tokio::test]
+async fn webhook_names_resolving_internally() {
+    let resolved = PublicResolver
+        .resolve(Name::from_str("localhost").unwrap())
+        .await;
+
+    assert!(resolved.is_err());
+}
+
+#[tokio::test]
+async fn webhook_redirects_not_followed() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _request = stream.read(&mut [0; 1024]).unwrap();
+        stream
+            .write_all(
+                b"HTTP/1.1 307 Temporary Redirect\r\n\
+                location: http://169.254.169.254/latest\r\n\
+                content-length: 0\r\n\r\n",
+            )
+            .unwrap();
+    });
+
+    let response = http_client()
+        .unwrap()
+        .post(format!("http://{addr}/hooks"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 307);
+}
@@ -0,0 +1,58 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use api_core::is_internal_ip;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+};
+use url::{Host, Url};
+
+/// Resolves the hosts deliveries are posted to, leaving out the addresses that only reach this
+/// host or its private network. A name that resolves to nothing else is not posted to at all
+pub(crate) struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| !is_internal_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// The client deliveries are posted with. It is kept apart from the one used for our own
+/// services, as redirects are not followed and every name is resolved with [`PublicResolver`]
+pub(crate) fn http_client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .redirect(Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+}
+
+/// Hosts given as an address are connected to without being resolved, so they are checked
+/// before a delivery is posted
+pub(crate) fn check_host(url: &str) -> Result<(), String> {
+    let url = Url::parse(url).map_err(|e| e.to_string())?;
+    let internal = match url.host() {
+        Some(Host::Ipv4(ip)) => is_internal_ip(ip.into()),
+        Some(Host::Ipv6(ip)) => is_internal_ip(ip.into()),
+        Some(Host::Domain(_)) => false,
+        None => true,
+    };
+
+    if internal {
+        Err(String::from(
+            "the webhook url does not point to a public host",
+        ))
+    } else {
+        Ok(())
+    }
+}
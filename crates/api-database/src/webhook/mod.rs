@@ -0,0 +1,359 @@
+pub(crate) mod client;
+
+use std::time::Duration;
+
+use api_core::{
+    api::{CoreError, ManageWebhooks},
+    reexports::uuid::Uuid,
+    DeliveryStatus, Listing, Webhook, WebhookDelivery, WebhookEvent,
+};
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use sha2::Sha256;
+use time::OffsetDateTime;
+use tracing::{debug, instrument, warn};
+
+use crate::{
+    collections::Collection,
+    entity::{
+        create_thing_from_id,
+        outbox::DatabaseEntityOutbox,
+        webhook::{DatabaseEntityWebhook, DatabaseEntityWebhookDelivery},
+    },
+    map_db_error,
+    outbox::{backoff, DueEntry, LEASE},
+    Client,
+};
+
+/// `sha256=` followed by the hex encoded signature of the payload
+const SIGNATURE_HEADER: &str = "x-sellershut-signature";
+/// The unix time the payload was signed at, which is part of what is signed
+const TIMESTAMP_HEADER: &str = "x-sellershut-timestamp";
+const EVENT_HEADER: &str = "x-sellershut-event";
+/// The id of the change, shared by every retry of it
+const DELIVERY_HEADER: &str = "x-sellershut-delivery";
+
+/// How many times a delivery is attempted before it is given up on
+const MAX_ATTEMPTS: u32 = 10;
+
+/// How long an endpoint has to answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Signs `{timestamp}.{payload}` with a webhook's secret, so receivers can check a payload came
+/// from us and reject ones replayed long after they were sent
+pub(crate) fn sign(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    id: Uuid,
+    #[serde(rename = "type")]
+    event: WebhookEvent,
+    listing: &'a Listing,
+}
+
+/// Queues a delivery of a change to every webhook subscribed to it
+pub(crate) async fn enqueue(
+    client: &Client,
+    entry: &DatabaseEntityOutbox,
+) -> Result<(), CoreError> {
+    let event = entry.webhook_event();
+    let event_id = entry.event_id()?;
+    let listing = Listing::try_from(entry.listing.clone())?;
+
+    let payload = serde_json::to_string(&Payload {
+        id: event_id,
+        event,
+        listing: &listing,
+    })
+    .map_err(|e| CoreError::Other(e.to_string()))?;
+
+    client
+        .client
+        .query(
+            "FOR $webhook IN (
+                SELECT VALUE id FROM type::table($webhook_tbl) WHERE $event INSIDE events
+            ) {
+                CREATE type::thing($table, <string> rand::uuid::v7()) CONTENT {
+                    webhook: $webhook,
+                    event: $event,
+                    event_id: $event_id,
+                    payload: $payload,
+                    status: 'pending',
+                    attempts: 0,
+                    response_status: NONE,
+                    error: NONE,
+                    created: time::now(),
+                    delivered: NONE,
+                    available: time::now()
+                };
+            };",
+        )
+        .bind(("table", Collection::WebhookDelivery))
+        .bind(("webhook_tbl", Collection::Webhook))
+        .bind(("event", event))
+        .bind(("event_id", event_id.to_string()))
+        .bind(("payload", payload))
+        .await
+        .map_err(map_db_error)?
+        .check()
+        .map_err(map_db_error)?;
+
+    Ok(())
+}
+
+/// Posts up to `batch` of the deliveries that are due, oldest first. Like changes in the
+/// outbox, a delivery is claimed before it is posted so only one dispatcher posts it at a time
+async fn dispatch(client: &Client, batch: usize) -> Result<usize, CoreError> {
+    let mut due = client
+        .client
+        .query(
+            "SELECT id, created FROM type::table($table)
+            WHERE status = 'pending' AND available <= time::now()
+            ORDER BY created
+            LIMIT $batch",
+        )
+        .bind(("table", Collection::WebhookDelivery))
+        .bind(("batch", batch))
+        .await
+        .map_err(map_db_error)?;
+
+    let due: Vec<DueEntry> = due.take(0).map_err(map_db_error)?;
+    if due.is_empty() {
+        return Ok(0);
+    }
+    let ids: Vec<_> = due.into_iter().map(|entry| entry.id).collect();
+
+    let mut claimed = client
+        .client
+        .query(
+            "UPDATE $ids SET
+                attempts += 1,
+                available = time::now() + type::duration($lease)
+            WHERE status = 'pending' AND available <= time::now()
+            RETURN AFTER",
+        )
+        .bind(("ids", &ids))
+        .bind(("lease", format!("{}s", LEASE.as_secs())))
+        .await
+        .map_err(map_db_error)?;
+
+    let claimed: Vec<DatabaseEntityWebhookDelivery> = claimed.take(0).map_err(map_db_error)?;
+
+    let mut delivered = 0;
+    for delivery in claimed {
+        let webhook: Option<DatabaseEntityWebhook> = client
+            .client
+            .select(delivery.webhook.clone())
+            .await
+            .map_err(map_db_error)?;
+
+        let (status, response_status, error) = match webhook {
+            Some(webhook) => match post(client, &webhook, &delivery).await {
+                Ok(code) => (DeliveryStatus::Delivered, Some(code), None),
+                Err((code, error)) => {
+                    let status = if delivery.attempts >= MAX_ATTEMPTS {
+                        DeliveryStatus::Failed
+                    } else {
+                        DeliveryStatus::Pending
+                    };
+                    (status, code, Some(error))
+                }
+            },
+            None => (
+                DeliveryStatus::Failed,
+                None,
+                Some(String::from("the webhook was deleted")),
+            ),
+        };
+
+        if let Some(ref error) = error {
+            warn!(
+                id = %delivery.id,
+                attempts = delivery.attempts,
+                ?status,
+                "webhook not delivered: {error}"
+            );
+        } else {
+            delivered += 1;
+        }
+
+        client
+            .client
+            .query(
+                "UPDATE $id SET
+                    status = $status,
+                    response_status = $response_status,
+                    error = $error,
+                    available = time::now() + type::duration($retry_in),
+                    delivered = (IF $status = 'delivered' THEN time::now() ELSE NONE END)",
+            )
+            .bind(("id", &delivery.id))
+            .bind(("status", status))
+            .bind(("response_status", response_status))
+            .bind(("error", error))
+            .bind((
+                "retry_in",
+                format!("{}s", backoff(delivery.attempts).as_secs()),
+            ))
+            .await
+            .map_err(map_db_error)?
+            .check()
+            .map_err(map_db_error)?;
+    }
+
+    debug!(delivered, "webhooks delivered");
+    Ok(delivered)
+}
+
+/// Posts a delivery, returning the status code the endpoint answered with. Failures carry the
+/// status code too when the endpoint answered at all
+async fn post(
+    client: &Client,
+    webhook: &DatabaseEntityWebhook,
+    delivery: &DatabaseEntityWebhookDelivery,
+) -> Result<u16, (Option<u16>, String)> {
+    client::check_host(&webhook.url).map_err(|e| (None, e))?;
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp();
+
+    let response = client
+        .webhook_client
+        .post(&webhook.url)
+        .timeout(TIMEOUT)
+        .header(CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, delivery.event.to_string())
+        .header(DELIVERY_HEADER, &delivery.event_id)
+        .header(TIMESTAMP_HEADER, timestamp)
+        .header(
+            SIGNATURE_HEADER,
+            sign(&webhook.secret, timestamp, &delivery.payload),
+        )
+        .body(delivery.payload.clone())
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = response.status();
+    if status.is_success() {
+        Ok(status.as_u16())
+    } else {
+        Err((
+            Some(status.as_u16()),
+            format!("the endpoint answered {status}"),
+        ))
+    }
+}
+
+impl ManageWebhooks for Client {
+    #[instrument(skip(self, secret), err(Debug))]
+    async fn create_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[WebhookEvent],
+    ) -> Result<Webhook, CoreError> {
+        let mut item = self
+            .client
+            .query(
+                "CREATE ONLY type::thing($table, $id) CONTENT {
+                    url: type::string($url),
+                    secret: type::string($secret),
+                    events: $events,
+                    created: time::now()
+                }",
+            )
+            .bind(("table", Collection::Webhook))
+            .bind(("id", Uuid::now_v7().to_string()))
+            .bind(("url", url))
+            .bind(("secret", secret))
+            .bind(("events", events))
+            .await
+            .map_err(map_db_error)?;
+
+        let item: Option<DatabaseEntityWebhook> = item.take(0).map_err(map_db_error)?;
+
+        match item {
+            Some(e) => Webhook::try_from(e),
+            None => Err(CoreError::Unreachable),
+        }
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn delete_webhook(&self, id: &Uuid) -> Result<Option<Webhook>, CoreError> {
+        let mut item = self
+            .client
+            .query("DELETE type::thing($table, $id) RETURN BEFORE")
+            .bind(("table", Collection::Webhook))
+            .bind(("id", id.to_string()))
+            .await
+            .map_err(map_db_error)?;
+
+        let item: Option<DatabaseEntityWebhook> = item.take(0).map_err(map_db_error)?;
+
+        item.map(Webhook::try_from).transpose()
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_webhooks(&self) -> Result<impl ExactSizeIterator<Item = Webhook>, CoreError> {
+        let mut webhooks = self
+            .client
+            .query("SELECT * FROM type::table($table) ORDER BY created")
+            .bind(("table", Collection::Webhook))
+            .await
+            .map_err(map_db_error)?;
+
+        let webhooks: Vec<DatabaseEntityWebhook> = webhooks.take(0).map_err(map_db_error)?;
+
+        Ok(webhooks
+            .into_iter()
+            .map(Webhook::try_from)
+            .collect::<Result<Vec<_>, CoreError>>()?
+            .into_iter())
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: &Uuid,
+        limit: usize,
+    ) -> Result<impl ExactSizeIterator<Item = WebhookDelivery>, CoreError> {
+        let mut deliveries = self
+            .client
+            .query(
+                "SELECT * FROM type::table($table)
+                WHERE webhook = $webhook
+                ORDER BY created DESC
+                LIMIT $limit",
+            )
+            .bind(("table", Collection::WebhookDelivery))
+            .bind((
+                "webhook",
+                create_thing_from_id(Collection::Webhook, webhook_id),
+            ))
+            .bind(("limit", limit))
+            .await
+            .map_err(map_db_error)?;
+
+        let deliveries: Vec<DatabaseEntityWebhookDelivery> =
+            deliveries.take(0).map_err(map_db_error)?;
+
+        Ok(deliveries
+            .into_iter()
+            .map(WebhookDelivery::try_from)
+            .collect::<Result<Vec<_>, CoreError>>()?
+            .into_iter())
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn dispatch_webhooks(&self, batch: usize) -> Result<usize, CoreError> {
+        dispatch(self, batch).await
+    }
+}
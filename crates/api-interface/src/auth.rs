@@ -0,0 +1,183 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use api_core::{api::CoreError, reexports::uuid::Uuid};
+use api_database::{Client, UserType};
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest},
+    Context, ErrorExtensions, Guard, Request, ServerResult,
+};
+use async_trait::async_trait;
+use thiserror::Error;
+use tokio::sync::OnceCell;
+
+/// What a caller may do, granted by their token or by their account in the users service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    Buyer,
+    Seller,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    /// The roles held by every account of a users service type
+    pub fn for_user_type(user_type: &UserType) -> &'static [Role] {
+        match user_type {
+            UserType::INDIVIDUAL => &[Role::Buyer, Role::Seller],
+            UserType::COMPANY => &[Role::Seller],
+            UserType::Other(_) => &[],
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = match self {
+            Role::Buyer => "buyer",
+            Role::Seller => "seller",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        };
+        write!(f, "{role}")
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("unknown role: {0}")]
+pub struct UnknownRole(String);
+
+impl FromStr for Role {
+    type Err = UnknownRole;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "buyer" => Ok(Role::Buyer),
+            "seller" => Ok(Role::Seller),
+            "moderator" => Ok(Role::Moderator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(UnknownRole(s.to_owned())),
+        }
+    }
+}
+
+/// The authenticated caller of a request, attached to the request data by the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub user_id: Uuid,
+    /// Roles granted by the token. When empty they are looked up through the [`RoleResolver`]
+    pub roles: Vec<Role>,
+}
+
+impl Identity {
+    pub fn new(user_id: Uuid) -> Self {
+        Self {
+            user_id,
+            roles: Vec::new(),
+        }
+    }
+
+    pub fn with_roles(mut self, roles: impl IntoIterator<Item = Role>) -> Self {
+        self.roles = roles.into_iter().collect();
+        self
+    }
+}
+
+/// Looks up the roles of callers whose token did not carry any
+#[async_trait]
+pub trait RoleResolver: Send + Sync {
+    async fn roles(&self, user_id: &Uuid) -> Result<Vec<Role>, CoreError>;
+}
+
+#[async_trait]
+impl RoleResolver for Client {
+    async fn roles(&self, user_id: &Uuid) -> Result<Vec<Role>, CoreError> {
+        Ok(self
+            .user_type(user_id)
+            .await?
+            .map(|user_type| Role::for_user_type(&user_type).to_vec())
+            .unwrap_or_default())
+    }
+}
+
+pub(crate) type SharedRoleResolver = Arc<dyn RoleResolver>;
+
+/// The roles looked up for the caller of a request, so the users service is asked at most once
+#[derive(Default)]
+struct ResolvedRoles(OnceCell<Vec<Role>>);
+
+/// Gives every request a [`ResolvedRoles`] to keep the roles of its caller in
+pub(crate) struct RoleCache;
+
+impl ExtensionFactory for RoleCache {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RoleCache)
+    }
+}
+
+#[async_trait]
+impl Extension for RoleCache {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        next.run(ctx, request.data(ResolvedRoles::default())).await
+    }
+}
+
+pub(crate) fn extract_identity<'a>(context: &'a Context) -> async_graphql::Result<&'a Identity> {
+    context.data_opt::<Identity>().ok_or_else(|| {
+        async_graphql::Error::new("authentication required")
+            .extend_with(|_, e| e.set("code", "UNAUTHENTICATED"))
+    })
+}
+
+pub(crate) async fn extract_roles(context: &Context<'_>) -> async_graphql::Result<Vec<Role>> {
+    let identity = extract_identity(context)?;
+
+    if !identity.roles.is_empty() {
+        return Ok(identity.roles.clone());
+    }
+
+    let resolve = || async {
+        match context.data_opt::<SharedRoleResolver>() {
+            Some(resolver) => resolver
+                .roles(&identity.user_id)
+                .await
+                .map_err(|e| e.extend()),
+            None => Ok(Vec::new()),
+        }
+    };
+
+    match context.data_opt::<ResolvedRoles>() {
+        Some(resolved) => resolved.0.get_or_try_init(resolve).await.cloned(),
+        None => resolve().await,
+    }
+}
+
+/// Admins hold every role
+pub(crate) fn has_role(roles: &[Role], role: Role) -> bool {
+    roles.contains(&role) || roles.contains(&Role::Admin)
+}
+
+/// Only lets callers holding a role resolve a field
+pub struct RoleGuard {
+    role: Role,
+}
+
+impl RoleGuard {
+    pub fn new(role: Role) -> Self {
+        Self { role }
+    }
+}
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        if has_role(&extract_roles(ctx).await?, self.role) {
+            Ok(())
+        } else {
+            Err(CoreError::Forbidden(format!("the {} role is required", self.role)).extend())
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use api_core::{
+    api::{ListingEdgeSource, Uuid},
+    Listing, ListingCondition, ListingEdges,
+};
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    ErrorExtensions,
+};
+use async_trait::async_trait;
+use tracing::instrument;
+
+use crate::Database;
+
+/// The [`DataLoader`] by-id lookups of listings go through
+pub(crate) type ListingDataLoader<D> = DataLoader<ListingLoader<D>>;
+
+/// Creates a [`DataLoader`] which runs its batches on the tokio runtime
+pub(crate) fn data_loader<T: Loader<Uuid>>(loader: T) -> DataLoader<T> {
+    DataLoader::new(loader, tokio::spawn)
+}
+
+/// Batches by-id lookups of listings into a single query
+pub(crate) struct ListingLoader<D>(pub(crate) D);
+
+impl<D: Database> Loader<Uuid> for ListingLoader<D> {
+    type Value = Listing;
+    type Error = async_graphql::Error;
+
+    #[instrument(skip(self), err(Debug))]
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, Listing>, Self::Error> {
+        let listings = self
+            .0
+            .get_listings_by_ids(keys)
+            .await
+            .map_err(|e| e.extend())?;
+
+        Ok(listings.map(|listing| (listing.id, listing)).collect())
+    }
+}
+
+/// Batches lookups of the edges of listings into a single query
+pub(crate) struct EdgeLoader<D>(D);
+
+impl<D: Database> Loader<Uuid> for EdgeLoader<D> {
+    type Value = ListingEdges;
+    type Error = async_graphql::Error;
+
+    #[instrument(skip(self), err(Debug))]
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, ListingEdges>, Self::Error> {
+        self.0
+            .get_edges_by_listing_ids(keys)
+            .await
+            .map_err(|e| e.extend())
+    }
+}
+
+/// Batches lookups of the conditions of listings into a single query
+pub(crate) struct ConditionLoader<D>(D);
+
+impl<D: Database> Loader<Uuid> for ConditionLoader<D> {
+    type Value = ListingCondition;
+    type Error = async_graphql::Error;
+
+    #[instrument(skip(self), err(Debug))]
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, ListingCondition>, Self::Error> {
+        self.0
+            .get_conditions_by_listing_ids(keys)
+            .await
+            .map_err(|e| e.extend())
+    }
+}
+
+/// Resolves the relations of listings through batching loaders
+pub(crate) struct RelationLoader<D: Database> {
+    edges: DataLoader<EdgeLoader<D>>,
+    conditions: DataLoader<ConditionLoader<D>>,
+}
+
+impl<D: Database> RelationLoader<D> {
+    pub(crate) fn new(database: D) -> Self {
+        Self {
+            edges: data_loader(EdgeLoader(database.clone())),
+            conditions: data_loader(ConditionLoader(database)),
+        }
+    }
+}
+
+#[async_trait]
+impl<D: Database> ListingEdgeSource for RelationLoader<D> {
+    async fn listing_edges(
+        &self,
+        listing_id: &Uuid,
+    ) -> async_graphql::Result<Option<ListingEdges>> {
+        self.edges.load_one(*listing_id).await
+    }
+
+    async fn listing_condition(
+        &self,
+        listing_id: &Uuid,
+    ) -> async_graphql::Result<Option<ListingCondition>> {
+        self.conditions.load_one(*listing_id).await
+    }
+}
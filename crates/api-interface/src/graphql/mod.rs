@@ -1,14 +1,27 @@
+pub(crate) mod loader;
 pub(crate) mod mutation;
 pub(crate) mod query;
 pub(crate) mod subscription;
 
-use api_database::Client;
 use async_graphql::Context;
 use tracing::error;
 
-pub(crate) fn extract_db<'a>(context: &'a Context) -> async_graphql::Result<&'a Client> {
-    context.data::<Client>().map_err(|db| {
+use crate::Database;
+
+use self::loader::ListingDataLoader;
+
+pub(crate) fn extract_db<'a, D: Database>(context: &'a Context) -> async_graphql::Result<&'a D> {
+    context.data::<D>().map_err(|db| {
         error!("{}", db.message);
         "Internal database error".into()
     })
 }
+
+pub(crate) fn extract_listing_loader<'a, D: Database>(
+    context: &'a Context,
+) -> async_graphql::Result<&'a ListingDataLoader<D>> {
+    context.data::<ListingDataLoader<D>>().map_err(|loader| {
+        error!("{}", loader.message);
+        "Internal database error".into()
+    })
+}
@@ -1,24 +1,61 @@
+use std::marker::PhantomData;
+
 use api_core::{
-    api::{MutateListings, Uuid},
-    Listing,
+    api::{CoreError, Uuid},
+    Listing, ListingStatus,
 };
-use api_database::Client;
-use async_graphql::{Context, InputObject, Object};
+use async_graphql::{Context, ErrorExtensions, InputObject, Object};
 use tracing::instrument;
 
-#[derive(Default, Debug)]
-pub struct ListingMutation;
+use crate::{
+    auth::{extract_identity, extract_roles, has_role, Role, RoleGuard},
+    graphql::extract_db,
+    Database,
+};
+
+pub struct ListingMutation<D>(PhantomData<D>);
+
+impl<D> Default for ListingMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[derive(InputObject, Debug)]
 pub struct MetaData {
     pub category_id: Uuid,
     pub condition_id: Uuid,
-    pub user_id: Uuid,
+}
+
+/// The seller a change to a listing is made on behalf of. Moderators may change any listing
+async fn acting_seller<D: Database>(
+    ctx: &Context<'_>,
+    database: &D,
+    id: &Uuid,
+) -> async_graphql::Result<Uuid> {
+    let identity = extract_identity(ctx)?;
+
+    match database
+        .get_listing_owner(id)
+        .await
+        .map_err(|e| e.extend())?
+    {
+        Some(owner)
+            if owner != identity.user_id
+                && has_role(&extract_roles(ctx).await?, Role::Moderator) =>
+        {
+            Ok(owner)
+        }
+        // the storage layer rejects callers that do not sell the listing
+        Some(_) => Ok(identity.user_id),
+        None => Err(CoreError::Database(format!("listing: {id} does not exist")).extend()),
+    }
 }
 
 #[Object]
-impl ListingMutation {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Database> ListingMutation<D> {
+    #[graphql(guard = "RoleGuard::new(Role::Seller)")]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn create_listing(
         &self,
         ctx: &Context<'_>,
@@ -26,12 +63,13 @@ impl ListingMutation {
         metadata: MetaData,
         #[graphql(default = 1)] quantity: usize,
     ) -> async_graphql::Result<Listing> {
-        let database = ctx.data::<Client>()?;
+        let identity = extract_identity(ctx)?;
+        let database = extract_db::<D>(ctx)?;
 
         match database
             .create_listing(
                 &input,
-                &metadata.user_id,
+                &identity.user_id,
                 &metadata.category_id,
                 &metadata.condition_id,
                 quantity,
@@ -39,11 +77,11 @@ impl ListingMutation {
             .await
         {
             Ok(listing) => Ok(listing),
-            Err(e) => Err(e.into()),
+            Err(e) => Err(e.extend()),
         }
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn update_listing(
         &self,
         ctx: &Context<'_>,
@@ -52,13 +90,14 @@ impl ListingMutation {
         metadata: MetaData,
         #[graphql(default = 1)] quantity: usize,
     ) -> async_graphql::Result<Option<Listing>> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
+        let seller = acting_seller(ctx, database, &id).await?;
 
         match database
             .update_listing(
                 &id,
                 &input,
-                &metadata.user_id,
+                &seller,
                 &metadata.category_id,
                 &metadata.condition_id,
                 quantity,
@@ -66,22 +105,63 @@ impl ListingMutation {
             .await
         {
             Ok(listing) => Ok(listing),
-            Err(e) => Err(e.into()),
+            Err(e) => Err(e.extend()),
         }
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn delete_listing(
         &self,
         ctx: &Context<'_>,
         id: Uuid,
-        user_id: Uuid,
     ) -> async_graphql::Result<Option<Listing>> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
+        let seller = acting_seller(ctx, database, &id).await?;
+
+        match database.delete_listing(&id, &seller).await {
+            Ok(listing) => Ok(listing),
+            Err(e) => Err(e.extend()),
+        }
+    }
+
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn restore_listing(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+    ) -> async_graphql::Result<Option<Listing>> {
+        let database = extract_db::<D>(ctx)?;
+        let seller = acting_seller(ctx, database, &id).await?;
+
+        match database.restore_listing(&id, &seller).await {
+            Ok(listing) => Ok(listing),
+            Err(e) => Err(e.extend()),
+        }
+    }
+
+    /// Moves a listing to another status. Only moderators may remove a listing, as it cannot be
+    /// moved out of that status again
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn transition_listing(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        status: ListingStatus,
+    ) -> async_graphql::Result<Option<Listing>> {
+        let database = extract_db::<D>(ctx)?;
+        let seller = acting_seller(ctx, database, &id).await?;
+
+        if status == ListingStatus::Removed
+            && !has_role(&extract_roles(ctx).await?, Role::Moderator)
+        {
+            return Err(
+                CoreError::Forbidden(format!("only moderators may remove listing: {id}")).extend(),
+            );
+        }
 
-        match database.delete_listing(&id, &user_id).await {
+        match database.transition_listing(&id, &seller, status).await {
             Ok(listing) => Ok(listing),
-            Err(e) => Err(e.into()),
+            Err(e) => Err(e.extend()),
         }
     }
 }
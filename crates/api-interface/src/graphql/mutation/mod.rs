@@ -1,12 +1,26 @@
 use std::fmt::Display;
 
+use api_core::ListingAction;
 use async_graphql::Enum;
 
+use crate::Database;
+
 pub(crate) mod listing;
 pub(crate) mod upload;
+pub(crate) mod webhook;
+
+#[derive(async_graphql::MergedObject)]
+pub struct Mutation<D: Database>(
+    listing::ListingMutation<D>,
+    upload::UploadMutation<D>,
+    webhook::WebhookMutation<D>,
+);
 
-#[derive(async_graphql::MergedObject, Default)]
-pub struct Mutation(listing::ListingMutation, upload::UploadMutation);
+impl<D: Database> Default for Mutation<D> {
+    fn default() -> Self {
+        Self(Default::default(), Default::default(), Default::default())
+    }
+}
 
 #[derive(Enum, Eq, PartialEq, Copy, Clone, Debug)]
 pub(crate) enum MutationType {
@@ -29,3 +43,13 @@ impl Display for MutationType {
         )
     }
 }
+
+impl From<ListingAction> for MutationType {
+    fn from(action: ListingAction) -> Self {
+        match action {
+            ListingAction::Created => Self::Created,
+            ListingAction::Updated => Self::Updated,
+            ListingAction::Deleted => Self::Deleted,
+        }
+    }
+}
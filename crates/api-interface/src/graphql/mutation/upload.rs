@@ -1,10 +1,21 @@
-use api_core::api::LocalMutateListings;
-use api_database::Client;
+use std::marker::PhantomData;
+
 use async_graphql::{Context, Object, Result, SimpleObject, Upload};
 use tracing::instrument;
 
-#[derive(Default, Debug)]
-pub struct UploadMutation;
+use crate::{
+    auth::{Role, RoleGuard},
+    graphql::extract_db,
+    Database,
+};
+
+pub struct UploadMutation<D>(PhantomData<D>);
+
+impl<D> Default for UploadMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[derive(Clone, SimpleObject)]
 pub struct FileInfo {
@@ -13,10 +24,11 @@ pub struct FileInfo {
 }
 
 #[Object]
-impl UploadMutation {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Database> UploadMutation<D> {
+    #[graphql(guard = "RoleGuard::new(Role::Seller)")]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn upload_images(&self, ctx: &Context<'_>, files: Vec<Upload>) -> Result<Vec<FileInfo>> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
 
         let mut futs = Vec::with_capacity(files.len());
 
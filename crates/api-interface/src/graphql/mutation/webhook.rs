@@ -0,0 +1,91 @@
+use std::marker::PhantomData;
+
+use api_core::{api::Uuid, is_internal_ip, Webhook, WebhookEvent};
+use async_graphql::{Context, ErrorExtensions, Object};
+use tracing::instrument;
+use url::{Host, Url};
+
+use crate::{
+    auth::{Role, RoleGuard},
+    graphql::extract_db,
+    Database,
+};
+
+/// Checks that events are only ever posted over https to a public host. The url is parsed the
+/// way the client posting to it does, so shorthand addresses like `127.1` are caught too. Hosts
+/// given by name are taken at their word here, bar the ones reserved for this host and local
+/// networks, and checked again once resolved when a delivery is posted
+fn check_webhook_url(url: &str) -> Result<(), &'static str> {
+    let url = Url::parse(url).map_err(|_| "webhook url is not valid")?;
+    if url.scheme() != "https" {
+        return Err("webhook url must use https");
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err("webhook url must not carry credentials");
+    }
+
+    let internal = match url.host() {
+        Some(Host::Ipv4(ip)) => is_internal_ip(ip.into()),
+        Some(Host::Ipv6(ip)) => is_internal_ip(ip.into()),
+        Some(Host::Domain(host)) => {
+            let host = host.trim_end_matches('.');
+            host.is_empty()
+                || host == "localhost"
+                || [".localhost", ".local", ".internal"]
+                    .iter()
+                    .any(|suffix| host.ends_with(suffix))
+        }
+        None => true,
+    };
+    if internal {
+        return Err("webhook url must point to a public host");
+    }
+
+    Ok(())
+}
+
+pub struct WebhookMutation<D>(PhantomData<D>);
+
+impl<D> Default for WebhookMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[Object]
+impl<D: Database> WebhookMutation<D> {
+    /// Posts the given events to `url`, which must be https and point to a public host, signed
+    /// with `secret`. The secret is stored as it is given, so it should not be reused elsewhere
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    #[instrument(skip(self, ctx, secret), err(Debug))]
+    async fn create_webhook(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(max_length = 2048))] url: String,
+        #[graphql(validator(min_length = 16, max_length = 256))] secret: String,
+        #[graphql(validator(min_items = 1))] events: Vec<WebhookEvent>,
+    ) -> async_graphql::Result<Webhook> {
+        check_webhook_url(&url)?;
+        let database = extract_db::<D>(ctx)?;
+
+        match database.create_webhook(&url, &secret, &events).await {
+            Ok(webhook) => Ok(webhook),
+            Err(e) => Err(e.extend()),
+        }
+    }
+
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn delete_webhook(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+    ) -> async_graphql::Result<Option<Webhook>> {
+        let database = extract_db::<D>(ctx)?;
+
+        match database.delete_webhook(&id).await {
+            Ok(webhook) => Ok(webhook),
+            Err(e) => Err(e.extend()),
+        }
+    }
+}
@@ -1,17 +1,27 @@
-use api_core::{api::QueryListingCondition, ListingCondition};
+use std::marker::PhantomData;
+
+use api_core::ListingCondition;
 use async_graphql::{Context, Object};
 use tracing::instrument;
 
-use crate::graphql::{extract_db, query::Params};
+use crate::{
+    graphql::{extract_db, query::Params},
+    Database,
+};
 
 use super::{pagination::paginate, ConnectionResult};
 
-#[derive(Default, Debug)]
-pub struct ListingConditionQuery;
+pub struct ListingConditionQuery<D>(PhantomData<D>);
+
+impl<D> Default for ListingConditionQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[Object]
-impl ListingConditionQuery {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Database> ListingConditionQuery<D> {
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn conditions(
         &self,
         ctx: &Context<'_>,
@@ -22,7 +32,7 @@ impl ListingConditionQuery {
     ) -> ConnectionResult<ListingCondition> {
         let p = Params::new(after, before, first, last)?;
 
-        let database = extract_db(ctx)?;
+        let database = extract_db::<D>(ctx)?;
 
         let tags = database.get_conditions().await?;
 
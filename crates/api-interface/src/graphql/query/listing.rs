@@ -1,79 +1,275 @@
-use api_core::{api::QueryListings, reexports::uuid::Uuid, Listing};
-use async_graphql::{Context, Object};
+use std::{marker::PhantomData, sync::Arc};
+
+use api_core::{
+    api::{CoreError, ListingEdgeSource},
+    reexports::uuid::Uuid,
+    Listing, ListingFilter, ListingSort, ListingStatus,
+};
+use async_graphql::{Context, ErrorExtensions, Object};
+use rust_decimal::Decimal;
 use tracing::instrument;
 
-use crate::graphql::{extract_db, query::Params};
+use crate::{
+    auth::{extract_roles, has_role, Identity, Role},
+    graphql::{extract_db, extract_listing_loader, query::Params},
+    Database,
+};
+
+use super::{
+    pagination::{
+        paginate_by_position, paginate_with, Base64Cursor, ListingCursor, SearchConnectionName,
+        SearchEdgeName,
+    },
+    ConnectionResult,
+};
+
+/// Only admins may see listings that are not active
+async fn check_status_visible(
+    ctx: &Context<'_>,
+    status: ListingStatus,
+) -> async_graphql::Result<()> {
+    if status == ListingStatus::Active || has_role(&extract_roles(ctx).await?, Role::Admin) {
+        Ok(())
+    } else {
+        Err(CoreError::Forbidden(format!("only admins may see {status} listings")).extend())
+    }
+}
+
+/// Hides a listing that is not active from everyone but admins and its seller. The seller is
+/// read through the batching edge loader, so listings checked together share a single lookup
+pub(crate) async fn visible_listing(
+    ctx: &Context<'_>,
+    listing: Option<Listing>,
+) -> async_graphql::Result<Option<Listing>> {
+    let Some(listing) = listing else {
+        return Ok(None);
+    };
+    if listing.status == ListingStatus::Active {
+        return Ok(Some(listing));
+    }
+    let Some(identity) = ctx.data_opt::<Identity>() else {
+        return Ok(None);
+    };
+
+    let seller = ctx
+        .data::<Arc<dyn ListingEdgeSource>>()?
+        .listing_edges(&listing.id)
+        .await?
+        .map(|edges| edges.user_id);
+    if seller == Some(identity.user_id) || has_role(&extract_roles(ctx).await?, Role::Admin) {
+        Ok(Some(listing))
+    } else {
+        Ok(None)
+    }
+}
+
+fn is_caller(ctx: &Context<'_>, user_id: Option<Uuid>) -> bool {
+    user_id.is_some() && ctx.data_opt::<Identity>().map(|identity| identity.user_id) == user_id
+}
+
+/// Pages through the listings matching a filter without loading the rest of them
+async fn paginate_listings<D: Database>(
+    database: &D,
+    filter: ListingFilter,
+    sort: ListingSort,
+    p: Params,
+) -> ConnectionResult<Listing, ListingCursor> {
+    paginate_with(p, sort, 100, |page| {
+        database.find_listings(&filter, sort, page)
+    })
+    .await
+}
 
-use super::{pagination::paginate, ConnectionResult};
+pub struct ListingQuery<D>(PhantomData<D>);
 
-#[derive(Default, Debug)]
-pub struct ListingQuery;
+impl<D> Default for ListingQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[Object]
-impl ListingQuery {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Database> ListingQuery<D> {
+    /// Listings matching every field set on `filter`. A status on the filter takes precedence
+    /// over the `status` argument
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn listings(
         &self,
         ctx: &Context<'_>,
+        #[graphql(default_with = "ListingStatus::Active")] status: ListingStatus,
+        filter: Option<ListingFilter>,
+        #[graphql(default)] order_by: ListingSort,
         #[graphql(validator(min_length = 1, max_length = 100))] after: Option<String>,
         #[graphql(validator(min_length = 1, max_length = 100))] before: Option<String>,
         #[graphql(validator(minimum = 1, maximum = 100))] first: Option<i32>,
         #[graphql(validator(minimum = 1, maximum = 100))] last: Option<i32>,
-    ) -> ConnectionResult<Listing> {
+    ) -> ConnectionResult<Listing, ListingCursor> {
         let p = Params::new(after, before, first, last)?;
+        let mut filter = filter.unwrap_or_default();
+        let status = *filter.status.get_or_insert(status);
+        // sellers may always see their own listings
+        if !is_caller(ctx, filter.user_id) {
+            check_status_visible(ctx, status).await?;
+        }
 
-        let database = extract_db(ctx)?;
+        let database = extract_db::<D>(ctx)?;
+
+        paginate_listings(database, filter, order_by, p).await
+    }
 
-        let listings = database.get_listings().await?;
+    /// Resolves references to listings made by other subgraphs
+    #[graphql(entity)]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn find_listing_by_id(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+    ) -> async_graphql::Result<Option<Listing>> {
+        let listings = extract_listing_loader::<D>(ctx)?;
 
-        paginate(listings, p, 100).await
+        visible_listing(ctx, listings.load_one(id).await?).await
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn listing_by_id(
         &self,
         ctx: &Context<'_>,
         id: Uuid,
     ) -> async_graphql::Result<Option<Listing>> {
-        let database = extract_db(ctx)?;
+        let listings = extract_listing_loader::<D>(ctx)?;
 
-        database.get_listing_by_id(&id).await.map_err(|e| e.into())
+        visible_listing(ctx, listings.load_one(id).await?).await
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn listing_in_category(
         &self,
         ctx: &Context<'_>,
         category_id: Uuid,
+        #[graphql(default_with = "ListingStatus::Active")] status: ListingStatus,
+        #[graphql(validator(min_length = 1, max_length = 100))] after: Option<String>,
+        #[graphql(validator(min_length = 1, max_length = 100))] before: Option<String>,
+        #[graphql(validator(minimum = 1, maximum = 100))] first: Option<i32>,
+        #[graphql(validator(minimum = 1, maximum = 100))] last: Option<i32>,
+    ) -> ConnectionResult<Listing, ListingCursor> {
+        let p = Params::new(after, before, first, last)?;
+        check_status_visible(ctx, status).await?;
+        let database = extract_db::<D>(ctx)?;
+
+        let filter = ListingFilter {
+            category_id: Some(category_id),
+            status: Some(status),
+            ..Default::default()
+        };
+
+        paginate_listings(database, filter, ListingSort::default(), p).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn listings_by_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: Uuid,
+        #[graphql(default_with = "ListingStatus::Active")] status: ListingStatus,
+        #[graphql(validator(min_length = 1, max_length = 100))] after: Option<String>,
+        #[graphql(validator(min_length = 1, max_length = 100))] before: Option<String>,
+        #[graphql(validator(minimum = 1, maximum = 100))] first: Option<i32>,
+        #[graphql(validator(minimum = 1, maximum = 100))] last: Option<i32>,
+    ) -> ConnectionResult<Listing, ListingCursor> {
+        let p = Params::new(after, before, first, last)?;
+        // sellers may always see their own listings
+        if !is_caller(ctx, Some(user_id)) {
+            check_status_visible(ctx, status).await?;
+        }
+        let database = extract_db::<D>(ctx)?;
+
+        let filter = ListingFilter {
+            user_id: Some(user_id),
+            status: Some(status),
+            ..Default::default()
+        };
+
+        paginate_listings(database, filter, ListingSort::default(), p).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn listings_with_tags(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(min_items = 1, max_items = 100))] tags: Vec<Uuid>,
+        #[graphql(default_with = "ListingStatus::Active")] status: ListingStatus,
+        #[graphql(validator(min_length = 1, max_length = 100))] after: Option<String>,
+        #[graphql(validator(min_length = 1, max_length = 100))] before: Option<String>,
+        #[graphql(validator(minimum = 1, maximum = 100))] first: Option<i32>,
+        #[graphql(validator(minimum = 1, maximum = 100))] last: Option<i32>,
+    ) -> ConnectionResult<Listing, ListingCursor> {
+        let p = Params::new(after, before, first, last)?;
+        check_status_visible(ctx, status).await?;
+        let database = extract_db::<D>(ctx)?;
+
+        let filter = ListingFilter {
+            tags,
+            status: Some(status),
+            ..Default::default()
+        };
+
+        paginate_listings(database, filter, ListingSort::default(), p).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn listings_in_price_range(
+        &self,
+        ctx: &Context<'_>,
+        min: Decimal,
+        max: Decimal,
+        #[graphql(default_with = "ListingStatus::Active")] status: ListingStatus,
         #[graphql(validator(min_length = 1, max_length = 100))] after: Option<String>,
         #[graphql(validator(min_length = 1, max_length = 100))] before: Option<String>,
         #[graphql(validator(minimum = 1, maximum = 100))] first: Option<i32>,
         #[graphql(validator(minimum = 1, maximum = 100))] last: Option<i32>,
-    ) -> ConnectionResult<Listing> {
+    ) -> ConnectionResult<Listing, ListingCursor> {
+        if min > max {
+            return Err("min cannot be greater than max".into());
+        }
         let p = Params::new(after, before, first, last)?;
-        let database = extract_db(ctx)?;
+        check_status_visible(ctx, status).await?;
+        let database = extract_db::<D>(ctx)?;
 
-        let listings = database.get_listings_in_category(&category_id).await?;
+        let filter = ListingFilter {
+            min_price: Some(min),
+            max_price: Some(max),
+            status: Some(status),
+            ..Default::default()
+        };
 
-        paginate(listings, p, 100).await
+        paginate_listings(database, filter, ListingSort::default(), p).await
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn search(
         &self,
         ctx: &Context<'_>,
         #[graphql(validator(min_length = 1, max_length = 100))] query: String,
+        #[graphql(default_with = "ListingStatus::Active")] status: ListingStatus,
         #[graphql(validator(min_length = 1, max_length = 100))] after: Option<String>,
         #[graphql(validator(min_length = 1, max_length = 100))] before: Option<String>,
         #[graphql(validator(minimum = 1, maximum = 100))] first: Option<i32>,
         #[graphql(validator(minimum = 1, maximum = 100))] last: Option<i32>,
-    ) -> ConnectionResult<Listing> {
+    ) -> ConnectionResult<Listing, Base64Cursor, SearchConnectionName, SearchEdgeName> {
         let p = Params::new(after, before, first, last)?;
+        check_status_visible(ctx, status).await?;
 
-        let database = extract_db(ctx)?;
-
-        let listings = database.search(&query).await?;
+        let database = extract_db::<D>(ctx)?;
 
-        paginate(listings, p, 100).await
+        paginate_by_position(p, 100, |offset, limit| {
+            database.search(&query, status, offset, limit)
+        })
+        .await
     }
 }
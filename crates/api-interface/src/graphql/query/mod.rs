@@ -1,15 +1,38 @@
-use async_graphql::connection::{Connection, EmptyFields};
+use async_graphql::connection::{Connection, DefaultConnectionName, DefaultEdgeName, EmptyFields};
+
+use crate::Database;
 
 pub(crate) mod condition;
 pub(crate) mod listing;
+pub(crate) mod node;
 pub(crate) mod pagination;
+pub(crate) mod webhook;
+
+#[derive(async_graphql::MergedObject)]
+pub struct Query<D: Database>(
+    listing::ListingQuery<D>,
+    condition::ListingConditionQuery<D>,
+    node::NodeQuery<D>,
+    webhook::WebhookQuery<D>,
+);
 
-#[derive(async_graphql::MergedObject, Default)]
-pub struct Query(listing::ListingQuery, condition::ListingConditionQuery);
+impl<D: Database> Default for Query<D> {
+    fn default() -> Self {
+        Self(
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+    }
+}
 
-pub(crate) type ConnectionResult<T> = async_graphql::Result<
-    Connection<pagination::Base64Cursor, T, pagination::ConnectionFields, EmptyFields>,
->;
+pub(crate) type ConnectionResult<
+    T,
+    C = pagination::Base64Cursor,
+    N = DefaultConnectionName,
+    E = DefaultEdgeName,
+> = async_graphql::Result<Connection<C, T, pagination::ConnectionFields, EmptyFields, N, E>>;
 
 /// Relay-compliant connection parameters to page results by cursor/page size
 pub struct Params {
@@ -0,0 +1,110 @@
+use std::marker::PhantomData;
+
+use api_core::{api::Uuid, GlobalId, Listing, ListingCondition, NodeType};
+use async_graphql::{Context, Interface, Object, ID};
+use futures_util::future::try_join_all;
+use tracing::instrument;
+
+use crate::{
+    graphql::{extract_db, extract_listing_loader},
+    Database,
+};
+
+use super::listing::visible_listing;
+
+/// An object that can be refetched by its globally unique id. Objects keep their `id` field,
+/// so the global id is a field of its own
+#[derive(Interface)]
+#[graphql(field(name = "global_id", ty = "ID", desc = "Globally unique id"))]
+pub enum Node {
+    Listing(Listing),
+    ListingCondition(ListingCondition),
+}
+
+/// Conditions are only listed as a whole, so they are loaded once per request
+async fn find_condition<D: Database>(
+    database: &D,
+    id: &Uuid,
+    conditions: &mut Option<Vec<ListingCondition>>,
+) -> async_graphql::Result<Option<Node>> {
+    let conditions = match conditions {
+        Some(conditions) => conditions,
+        None => conditions.insert(database.get_conditions().await?.collect()),
+    };
+
+    Ok(conditions
+        .iter()
+        .find(|condition| condition.id == *id)
+        .cloned()
+        .map(Node::ListingCondition))
+}
+
+pub struct NodeQuery<D>(PhantomData<D>);
+
+impl<D> Default for NodeQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[Object]
+impl<D: Database> NodeQuery<D> {
+    /// Fetches any object by its global id
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn node(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<Option<Node>> {
+        let GlobalId { node_type, id } = id.parse()?;
+
+        match node_type {
+            NodeType::Listing => {
+                let listings = extract_listing_loader::<D>(ctx)?;
+                let listing = visible_listing(ctx, listings.load_one(id).await?).await?;
+                Ok(listing.map(Node::Listing))
+            }
+            NodeType::ListingCondition => {
+                let database = extract_db::<D>(ctx)?;
+                find_condition(database, &id, &mut None).await
+            }
+        }
+    }
+
+    /// Fetches objects by their global ids, in the order the ids were given. Listings are
+    /// loaded in a single batch
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn nodes(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(max_items = 100))] ids: Vec<ID>,
+    ) -> async_graphql::Result<Vec<Option<Node>>> {
+        let database = extract_db::<D>(ctx)?;
+        let listings = extract_listing_loader::<D>(ctx)?;
+
+        let ids = ids
+            .iter()
+            .map(|id| id.parse::<GlobalId>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let listing_ids = ids
+            .iter()
+            .filter(|id| id.node_type == NodeType::Listing)
+            .map(|id| id.id);
+        let found = listings.load_many(listing_ids.clone()).await?;
+        // checked together, so the sellers of the listings are looked up in a single batch. The
+        // same listing may be asked for more than once
+        let mut visible =
+            try_join_all(listing_ids.map(|id| visible_listing(ctx, found.get(&id).cloned())))
+                .await?
+                .into_iter();
+
+        let mut conditions = None;
+        let mut nodes = Vec::with_capacity(ids.len());
+        for GlobalId { node_type, id } in &ids {
+            let node = match node_type {
+                NodeType::Listing => visible.next().flatten().map(Node::Listing),
+                NodeType::ListingCondition => find_condition(database, id, &mut conditions).await?,
+            };
+            nodes.push(node);
+        }
+
+        Ok(nodes)
+    }
+}
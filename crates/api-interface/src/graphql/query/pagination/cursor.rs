@@ -0,0 +1,138 @@
+use std::str::FromStr;
+
+use api_core::{reexports::uuid::Uuid, ListingKey, ListingSort, SortValue};
+use async_graphql::connection::CursorType;
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use rust_decimal::Decimal;
+use thiserror::Error;
+use time::OffsetDateTime;
+
+/// Bumped whenever the cursor layout changes, so cursors handed out before are rejected
+/// instead of being misread
+const VERSION: &str = "v1";
+
+/// Why a listing cursor was rejected
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ListingCursorError {
+    #[error("invalid cursor")]
+    Invalid,
+    #[error("unsupported cursor version: {0}")]
+    Version(String),
+    #[error("the cursor was made for the {0:?} order")]
+    Order(ListingSort),
+}
+
+/// Opaque cursor pointing at a listing by its sort key and id rather than by its position, so
+/// pages stay put when listings are added or removed between requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingCursor {
+    sort: ListingSort,
+    key: ListingKey,
+}
+
+fn sort_name(sort: ListingSort) -> &'static str {
+    match sort {
+        ListingSort::CreatedDesc => "created_desc",
+        ListingSort::CreatedAsc => "created_asc",
+        ListingSort::PriceAsc => "price_asc",
+        ListingSort::PriceDesc => "price_desc",
+        ListingSort::ExpiresAsc => "expires_asc",
+    }
+}
+
+fn parse_sort(name: &str) -> Option<ListingSort> {
+    [
+        ListingSort::CreatedDesc,
+        ListingSort::CreatedAsc,
+        ListingSort::PriceAsc,
+        ListingSort::PriceDesc,
+        ListingSort::ExpiresAsc,
+    ]
+    .into_iter()
+    .find(|sort| sort_name(*sort) == name)
+}
+
+fn parse_time(value: &str) -> Option<OffsetDateTime> {
+    let nanos = value.parse::<i128>().ok()?;
+    OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()
+}
+
+impl ListingCursor {
+    pub fn new(sort: ListingSort, key: ListingKey) -> Self {
+        Self { sort, key }
+    }
+
+    /// The key to page from, as long as the cursor was made for the same order
+    pub fn key_for(&self, sort: ListingSort) -> Result<ListingKey, ListingCursorError> {
+        if self.sort == sort {
+            Ok(self.key)
+        } else {
+            Err(ListingCursorError::Order(self.sort))
+        }
+    }
+
+    /// Returns a base64 string of `version:sort:id:value`
+    fn encode(&self) -> String {
+        let value = match self.key.value {
+            SortValue::Created(value) | SortValue::Expires(Some(value)) => {
+                value.unix_timestamp_nanos().to_string()
+            }
+            SortValue::Price(value) => value.to_string(),
+            SortValue::Expires(None) => String::new(),
+        };
+
+        BASE64_URL_SAFE_NO_PAD.encode(format!(
+            "{VERSION}:{}:{}:{value}",
+            sort_name(self.sort),
+            self.key.id
+        ))
+    }
+
+    fn decode(s: &str) -> Result<Self, ListingCursorError> {
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(s)
+            .map_err(|_| ListingCursorError::Invalid)?;
+        let cursor = String::from_utf8(bytes).map_err(|_| ListingCursorError::Invalid)?;
+
+        let mut parts = cursor.splitn(4, ':');
+        let version = parts.next().ok_or(ListingCursorError::Invalid)?;
+        if version != VERSION {
+            return Err(ListingCursorError::Version(version.to_owned()));
+        }
+
+        let (Some(sort), Some(id), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(ListingCursorError::Invalid);
+        };
+        let sort = parse_sort(sort).ok_or(ListingCursorError::Invalid)?;
+        let id = Uuid::from_str(id).map_err(|_| ListingCursorError::Invalid)?;
+
+        let value = match sort {
+            ListingSort::CreatedDesc | ListingSort::CreatedAsc => {
+                parse_time(value).map(SortValue::Created)
+            }
+            ListingSort::PriceAsc | ListingSort::PriceDesc => {
+                Decimal::from_str(value).ok().map(SortValue::Price)
+            }
+            ListingSort::ExpiresAsc if value.is_empty() => Some(SortValue::Expires(None)),
+            ListingSort::ExpiresAsc => {
+                parse_time(value).map(|value| SortValue::Expires(Some(value)))
+            }
+        }
+        .ok_or(ListingCursorError::Invalid)?;
+
+        Ok(Self::new(sort, ListingKey { value, id }))
+    }
+}
+
+/// Makes the `ListingCursor` compatible with Relay connections
+impl CursorType for ListingCursor {
+    type Error = ListingCursorError;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        ListingCursor::decode(s)
+    }
+
+    fn encode_cursor(&self) -> String {
+        self.encode()
+    }
+}
@@ -1,13 +1,18 @@
-use std::convert::Infallible;
+use std::{convert::Infallible, future::Future};
 
+use api_core::{api::CoreError, Listing, ListingSort, Page, PageRequest};
 use async_graphql::{
-    connection::{self, Connection, CursorType, Edge},
-    SimpleObject,
+    connection::{self, Connection, ConnectionNameType, CursorType, Edge, EdgeNameType},
+    ErrorExtensions, OutputType, SimpleObject,
 };
 use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
 
 use super::{ConnectionResult, Params};
 
+mod cursor;
+
+pub use cursor::ListingCursor;
+
 /// Base64 invalid states, used by `Base64Cursor`.
 pub enum Base64CursorError {
     /// Invalid cursor. This can happen if the base64 string is valid, but its contents don't
@@ -50,8 +55,8 @@ impl Base64Cursor {
 
         let cursor = String::from_utf8(bytes).map_err(|_| Base64CursorError::Invalid)?;
         let index = cursor
-            .split(':')
-            .last()
+            .rsplit(':')
+            .next()
             .map(|s| s.parse::<usize>())
             .ok_or(Base64CursorError::Invalid)?
             .map_err(|_| Base64CursorError::Invalid)?;
@@ -85,6 +90,23 @@ impl CursorType for Base64Cursor {
     }
 }
 
+/// Names search connections apart from the listing connections, whose cursors are keyset based
+pub struct SearchConnectionName;
+
+impl ConnectionNameType for SearchConnectionName {
+    fn type_name<T: OutputType>() -> String {
+        format!("{}SearchConnection", T::type_name())
+    }
+}
+
+pub struct SearchEdgeName;
+
+impl EdgeNameType for SearchEdgeName {
+    fn type_name<T: OutputType>() -> String {
+        format!("{}SearchEdge", T::type_name())
+    }
+}
+
 /// Additional fields to attach to the connection
 #[derive(SimpleObject)]
 pub struct ConnectionFields {
@@ -92,13 +114,43 @@ pub struct ConnectionFields {
     total_count: usize,
 }
 
+/// Calculates the `start..end` window of a result set to return. `before` is the end of the
+/// result set when no cursor was given
+fn window(
+    after: Option<Base64Cursor>,
+    before: usize,
+    first: Option<usize>,
+    last: Option<usize>,
+    default_page_size: usize,
+) -> (usize, usize) {
+    let after = after.map(|a| a.increment()).unwrap_or(0);
+
+    // Calculate start/end based on the provided first/last. Note that async-graphql disallows
+    // providing both (returning an error), so we can safely assume we have, at most, one of
+    // first or last.
+    match (first, last) {
+        // First
+        (Some(first), _) => (after, (after.saturating_add(first)).min(before)),
+        // Last
+        (_, Some(last)) => ((before.saturating_sub(last)).max(after), before),
+        // Default page size
+        _ => (after, (after.saturating_add(default_page_size)).min(before)),
+    }
+}
+
 /// Creates a new Relay-compliant connection. Iterator must implement `ExactSizeIterator` to
 /// determine page position in the total result set.
-pub async fn paginate<T: async_graphql::OutputType, I: ExactSizeIterator<Item = T>>(
+pub async fn paginate<T, I, N, E>(
     iter: I,
     p: Params,
     default_page_size: usize,
-) -> ConnectionResult<T> {
+) -> ConnectionResult<T, Base64Cursor, N, E>
+where
+    T: OutputType,
+    I: ExactSizeIterator<Item = T>,
+    N: ConnectionNameType,
+    E: EdgeNameType,
+{
     connection::query::<_, _, Base64Cursor, _, _, ConnectionFields, _, _, _, Infallible>(
         p.after,
         p.before,
@@ -107,22 +159,8 @@ pub async fn paginate<T: async_graphql::OutputType, I: ExactSizeIterator<Item =
         |after, before, first, last| async move {
             let iter_len = iter.len();
 
-            let (start, end) = {
-                let after = after.map(|a| a.increment()).unwrap_or(0);
-                let before: usize = before.map(|b| b.into()).unwrap_or(iter_len);
-
-                // Calculate start/end based on the provided first/last. Note that async-graphql disallows
-                // providing both (returning an error), so we can safely assume we have, at most, one of
-                // first or last.
-                match (first, last) {
-                    // First
-                    (Some(first), _) => (after, (after.saturating_add(first)).min(before)),
-                    // Last
-                    (_, Some(last)) => ((before.saturating_sub(last)).max(after), before),
-                    // Default page size
-                    _ => (after, default_page_size.min(before)),
-                }
-            };
+            let before = before.map(|b| b.into()).unwrap_or(iter_len);
+            let (start, end) = window(after, before, first, last, default_page_size);
 
             let mut connection = Connection::with_additional_fields(
                 start > 0,
@@ -141,3 +179,116 @@ pub async fn paginate<T: async_graphql::OutputType, I: ExactSizeIterator<Item =
     )
     .await
 }
+
+/// Creates a new Relay-compliant connection over results that are paged by position, such as
+/// search results ranked by relevance. `fetch` loads up to `limit` results from `offset` along
+/// with the size of the whole result set.
+pub async fn paginate_by_position<T, F, Fut, N, E>(
+    p: Params,
+    default_page_size: usize,
+    fetch: F,
+) -> ConnectionResult<T, Base64Cursor, N, E>
+where
+    T: OutputType,
+    F: Fn(usize, usize) -> Fut,
+    Fut: Future<Output = Result<Page<T>, CoreError>>,
+    N: ConnectionNameType,
+    E: EdgeNameType,
+{
+    connection::query::<_, _, Base64Cursor, _, _, ConnectionFields, _, _, _, async_graphql::Error>(
+        p.after,
+        p.before,
+        p.first,
+        p.last,
+        |after, before, first, last| async move {
+            // the last results can only be found from the end of the result set
+            let before = match before {
+                Some(before) => before.into(),
+                None if last.is_some() => fetch(0, 0).await.map_err(|e| e.extend())?.total,
+                None => usize::MAX,
+            };
+            let (start, end) = window(after, before, first, last, default_page_size);
+
+            let Page { items, total } = fetch(start, end.saturating_sub(start))
+                .await
+                .map_err(|e| e.extend())?;
+
+            let mut connection = Connection::with_additional_fields(
+                start > 0,
+                start + items.len() < total,
+                ConnectionFields { total_count: total },
+            );
+            connection.edges.extend(
+                (start..)
+                    .zip(items)
+                    .map(|(cursor, node)| Edge::new(Base64Cursor::new(cursor), node)),
+            );
+            Ok(connection)
+        },
+    )
+    .await
+}
+
+/// Creates a new Relay-compliant connection over listings that are paged by the storage layer.
+/// `fetch` loads a window of the listings along with the size of the whole result set, so only
+/// the requested page is ever read. Cursors point at listings by their key in `sort`, so the
+/// pages stay put when listings are added or removed in between requests.
+pub async fn paginate_with<F, Fut>(
+    p: Params,
+    sort: ListingSort,
+    default_page_size: usize,
+    fetch: F,
+) -> ConnectionResult<Listing, ListingCursor>
+where
+    F: Fn(PageRequest) -> Fut,
+    Fut: Future<Output = Result<Page<Listing>, CoreError>>,
+{
+    connection::query::<_, _, ListingCursor, _, _, ConnectionFields, _, _, _, async_graphql::Error>(
+        p.after,
+        p.before,
+        p.first,
+        p.last,
+        |after, before, first, last| async move {
+            let after = after.map(|cursor| cursor.key_for(sort)).transpose()?;
+            let before = before.map(|cursor| cursor.key_for(sort)).transpose()?;
+
+            let (page, limit) = match (first, last) {
+                (None, Some(last)) => (PageRequest::last(last + 1), last),
+                (first, _) => {
+                    let first = first.unwrap_or(default_page_size);
+                    (PageRequest::first(first + 1), first)
+                }
+            };
+
+            // one listing more than asked for tells whether there is another page
+            let Page { mut items, total } = fetch(page.with_after(after).with_before(before))
+                .await
+                .map_err(|e| e.extend())?;
+            let more = items.len() > limit;
+            if more && page.from_end {
+                items.remove(0);
+            } else {
+                items.truncate(limit);
+            }
+
+            let (has_previous, has_next) = if page.from_end {
+                (more, before.is_some())
+            } else {
+                (after.is_some(), more)
+            };
+
+            let mut connection = Connection::with_additional_fields(
+                has_previous,
+                has_next,
+                ConnectionFields { total_count: total },
+            );
+            connection.edges.extend(
+                items.into_iter().map(|listing| {
+                    Edge::new(ListingCursor::new(sort, sort.key(&listing)), listing)
+                }),
+            );
+            Ok(connection)
+        },
+    )
+    .await
+}
@@ -0,0 +1,50 @@
+use std::marker::PhantomData;
+
+use api_core::{api::Uuid, Webhook, WebhookDelivery};
+use async_graphql::{Context, ErrorExtensions, Object};
+use tracing::instrument;
+
+use crate::{
+    auth::{Role, RoleGuard},
+    graphql::extract_db,
+    Database,
+};
+
+pub struct WebhookQuery<D>(PhantomData<D>);
+
+impl<D> Default for WebhookQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[Object]
+impl<D: Database> WebhookQuery<D> {
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn webhooks(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Webhook>> {
+        let database = extract_db::<D>(ctx)?;
+
+        match database.get_webhooks().await {
+            Ok(webhooks) => Ok(webhooks.collect()),
+            Err(e) => Err(e.extend()),
+        }
+    }
+
+    /// The latest deliveries to a webhook, newest first
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn webhook_deliveries(
+        &self,
+        ctx: &Context<'_>,
+        webhook_id: Uuid,
+        #[graphql(default = 50, validator(minimum = 1, maximum = 100))] limit: usize,
+    ) -> async_graphql::Result<Vec<WebhookDelivery>> {
+        let database = extract_db::<D>(ctx)?;
+
+        match database.get_webhook_deliveries(&webhook_id, limit).await {
+            Ok(deliveries) => Ok(deliveries.collect()),
+            Err(e) => Err(e.extend()),
+        }
+    }
+}
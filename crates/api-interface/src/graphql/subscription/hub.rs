@@ -0,0 +1,195 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use api_core::ListingEvent;
+use futures_channel::mpsc::{self, Receiver, Sender};
+use futures_timer::Delay;
+use futures_util::{pin_mut, Stream, StreamExt};
+use slab::Slab;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+use crate::Database;
+
+/// How many changes a subscriber may fall behind before it is disconnected
+pub(crate) const SUBSCRIBER_BUFFER: usize = 256;
+
+/// How long to wait before opening the live query again once it ends, doubled on every attempt
+/// that fails until it reaches [`MAX_REOPEN_DELAY`]
+const MIN_REOPEN_DELAY: Duration = Duration::from_millis(100);
+const MAX_REOPEN_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct Hub {
+    /// Every subscriber along with an id that tells it apart from later ones reusing its key
+    subscribers: Slab<(u64, Sender<ListingEvent>)>,
+    next_id: u64,
+    /// The task reading the live query, along with the generation it was started in
+    upstream: Option<(u64, JoinHandle<()>)>,
+    generation: u64,
+}
+
+impl Hub {
+    fn record(&self) {
+        metrics::gauge!("listing_subscribers").set(self.subscribers.len() as f64);
+        metrics::gauge!("listing_live_queries").set(u8::from(self.upstream.is_some()));
+    }
+}
+
+fn lock(hub: &Mutex<Hub>) -> MutexGuard<'_, Hub> {
+    hub.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Fans a single live query out to every subscriber in the process. The live query is opened
+/// for the first subscriber, opened again if it ends and closed once the last one leaves
+#[derive(Clone)]
+pub(crate) struct ListingHub<D> {
+    database: D,
+    hub: Arc<Mutex<Hub>>,
+}
+
+impl<D: Database> ListingHub<D> {
+    pub(crate) fn new(database: D) -> Self {
+        Self {
+            database,
+            hub: Arc::default(),
+        }
+    }
+
+    /// Every change to listings from now on. Subscribers that fall too far behind are
+    /// disconnected, which ends their stream
+    pub(crate) fn subscribe(&self) -> HubReceiver {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER);
+        let mut hub = lock(&self.hub);
+
+        let id = hub.next_id;
+        hub.next_id += 1;
+        let key = hub.subscribers.insert((id, sender));
+
+        if hub.upstream.is_none() {
+            debug!("opening the listings live query");
+            hub.generation += 1;
+            let generation = hub.generation;
+            let task = tokio::spawn(run_upstream(
+                self.database.clone(),
+                Arc::clone(&self.hub),
+                generation,
+            ));
+            hub.upstream = Some((generation, task));
+        }
+        hub.record();
+
+        HubReceiver {
+            key,
+            id,
+            receiver,
+            hub: Arc::clone(&self.hub),
+        }
+    }
+
+    /// Whether the live query is open
+    #[cfg(test)]
+    pub(crate) fn is_live(&self) -> bool {
+        lock(&self.hub).upstream.is_some()
+    }
+}
+
+/// Reads the live query until nobody is left to listen, opening it again whenever it ends
+async fn run_upstream<D: Database>(database: D, hub: Arc<Mutex<Hub>>, generation: u64) {
+    let mut delay = MIN_REOPEN_DELAY;
+
+    'upstream: loop {
+        match database.live_listings().await {
+            Ok(events) => {
+                pin_mut!(events);
+                while let Some(event) = events.next().await {
+                    delay = MIN_REOPEN_DELAY;
+                    if !broadcast(&hub, &event) {
+                        break 'upstream;
+                    }
+                }
+                warn!("the listings live query ended");
+            }
+            Err(e) => error!("{e}"),
+        }
+
+        if lock(&hub).subscribers.is_empty() {
+            break;
+        }
+        debug!(?delay, "opening the listings live query again");
+        Delay::new(delay).await;
+        delay = (delay * 2).min(MAX_REOPEN_DELAY);
+    }
+
+    let mut hub = lock(&hub);
+    // a newer live query may have been opened after this one was closed
+    if hub
+        .upstream
+        .as_ref()
+        .is_some_and(|(started, _)| *started == generation)
+    {
+        debug!("closing the listings live query");
+        hub.upstream = None;
+        hub.record();
+    }
+}
+
+/// Sends a change to every subscriber, returning whether any are left
+fn broadcast(hub: &Mutex<Hub>, event: &ListingEvent) -> bool {
+    let mut hub = lock(hub);
+
+    hub.subscribers
+        .retain(|_, (_, sender)| match sender.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(e) if e.is_full() => {
+                warn!("disconnecting a listings subscriber that fell behind");
+                metrics::counter!("listing_subscribers_lagged_total").increment(1);
+                false
+            }
+            Err(_) => false,
+        });
+    hub.record();
+
+    !hub.subscribers.is_empty()
+}
+
+/// The changes sent to one subscriber of a [`ListingHub`]. Dropping it unsubscribes
+pub(crate) struct HubReceiver {
+    key: usize,
+    id: u64,
+    receiver: Receiver<ListingEvent>,
+    hub: Arc<Mutex<Hub>>,
+}
+
+impl Stream for HubReceiver {
+    type Item = ListingEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for HubReceiver {
+    fn drop(&mut self) {
+        let mut hub = lock(&self.hub);
+
+        if hub
+            .subscribers
+            .get(self.key)
+            .is_some_and(|(id, _)| *id == self.id)
+        {
+            hub.subscribers.remove(self.key);
+        }
+        if hub.subscribers.is_empty() {
+            if let Some((_, task)) = hub.upstream.take() {
+                debug!("closing the listings live query");
+                task.abort();
+            }
+        }
+        hub.record();
+    }
+}
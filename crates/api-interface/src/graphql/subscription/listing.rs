@@ -1,20 +1,160 @@
-use api_core::{api::QueryListings, Listing};
-use async_graphql::{Context, Object, Result, Subscription};
-use futures_util::Stream;
+use std::{future::ready, marker::PhantomData, sync::Arc};
 
-use crate::graphql::{extract_db, mutation::MutationType, subscription::ListingChanged};
+use api_core::{
+    api::{CoreError, ListingEdgeSource},
+    reexports::uuid::Uuid,
+    Listing, ListingAction, ListingDiff, ListingFilter, ListingStatus, WatchedListing,
+};
+use async_graphql::{Context, ErrorExtensions, Object, Result, Subscription};
+use futures_util::{pin_mut, Stream, StreamExt};
+use rust_decimal::Decimal;
+use tracing::error;
 
-#[derive(Default)]
-pub struct ListingSubscription;
+use crate::{
+    auth::{extract_roles, has_role},
+    graphql::{
+        extract_db,
+        mutation::MutationType,
+        subscription::{hub::ListingHub, ListingChanged},
+    },
+    Database, Identity, Role,
+};
+
+/// Whether the caller may see listings that are not active
+async fn is_admin(ctx: &Context<'_>) -> Result<bool> {
+    if ctx.data_opt::<Identity>().is_none() {
+        return Ok(false);
+    }
+
+    Ok(has_role(&extract_roles(ctx).await?, Role::Admin))
+}
+
+pub struct ListingSubscription<D>(PhantomData<D>);
+
+impl<D> Default for ListingSubscription<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[Subscription]
-impl ListingSubscription {
+impl<D: Database> ListingSubscription<D> {
+    /// Changes to listings as they happen, limited to the ones matching every argument that is
+    /// set. Every subscriber in the process shares a single live query. Listings that were
+    /// purged no longer have a seller or category, so their deletions are left out once either
+    /// is filtered on. Listings that are not active are only sent to admins
     async fn listings<'a>(
         &'a self,
         ctx: &'a Context<'a>,
-    ) -> Result<impl Stream<Item = Listing> + 'a> {
-        let database = extract_db(ctx)?;
-        Ok(database.live_listings().await?)
+        mutation_types: Option<Vec<MutationType>>,
+        category_id: Option<Uuid>,
+        seller_id: Option<Uuid>,
+        min_price: Option<Decimal>,
+        max_price: Option<Decimal>,
+    ) -> Result<impl Stream<Item = ListingChanged> + 'a> {
+        let hub = ctx.data::<ListingHub<D>>()?;
+        let edges = ctx.data::<Arc<dyn ListingEdgeSource>>()?;
+        let is_admin = is_admin(ctx).await?;
+        let filter = ListingFilter {
+            min_price,
+            max_price,
+            ..Default::default()
+        };
+
+        let changes = hub.subscribe().filter(move |event| {
+            let mutation_type = MutationType::from(event.action);
+            ready(
+                mutation_types
+                    .as_ref()
+                    .map_or(true, |types| types.contains(&mutation_type))
+                    && filter.matches(&event.listing)
+                    && (is_admin || event.listing.status == ListingStatus::Active),
+            )
+        });
+
+        Ok(changes.filter_map(move |event| async move {
+            if category_id.is_some() || seller_id.is_some() {
+                let listing_edges = match edges.listing_edges(&event.listing.id).await {
+                    Ok(listing_edges) => listing_edges?,
+                    Err(e) => {
+                        error!("{}", e.message);
+                        return None;
+                    }
+                };
+                let related = category_id.map_or(true, |id| id == listing_edges.category_id)
+                    && seller_id.map_or(true, |id| id == listing_edges.user_id);
+                if !related {
+                    return None;
+                }
+            }
+
+            Some(ListingChanged::from(event))
+        }))
+    }
+
+    /// Changes to the price, status and quantity of a single listing. The stream ends once the
+    /// listing is deleted. Callers other than admins and its seller hear nothing while the
+    /// listing is not active. Like `listings`, it is served from the live query every
+    /// subscriber in the process shares
+    async fn listing_updated<'a>(
+        &'a self,
+        ctx: &'a Context<'a>,
+        id: Uuid,
+    ) -> Result<impl Stream<Item = ListingDiff> + 'a> {
+        let database = extract_db::<D>(ctx)?;
+        let hub = ctx.data::<ListingHub<D>>()?;
+        let edges = ctx.data::<Arc<dyn ListingEdgeSource>>()?;
+
+        // listen before reading the listing so no change falls in between, bar the ones made
+        // while the shared live query is first opened
+        let events = hub
+            .subscribe()
+            .filter(move |event| ready(event.listing.id == id));
+        let listing = database.get_listing_by_id(&id).await?;
+        let sees_inactive = match ctx.data_opt::<Identity>() {
+            Some(identity) => {
+                database.get_listing_owner(&id).await? == Some(identity.user_id)
+                    || is_admin(ctx).await?
+            }
+            None => false,
+        };
+        let Some(listing) =
+            listing.filter(|listing| sees_inactive || listing.status == ListingStatus::Active)
+        else {
+            return Err(CoreError::Other(format!("listing {id} does not exist")).extend());
+        };
+        let quantity = database
+            .get_edges_by_listing_ids(&[id])
+            .await?
+            .get(&id)
+            .map_or(0, |e| e.quantity);
+        let mut previous = WatchedListing::new(&listing, quantity);
+
+        Ok(async_stream::stream! {
+            pin_mut!(events);
+            while let Some(event) = events.next().await {
+                if event.action == ListingAction::Deleted {
+                    break;
+                }
+                // diffs are taken from the last state the caller saw, so a hidden one never leaks
+                if !sees_inactive && event.listing.status != ListingStatus::Active {
+                    continue;
+                }
+                let quantity = match edges.listing_edges(&id).await {
+                    Ok(listing_edges) => listing_edges.map_or(previous.quantity, |e| e.quantity),
+                    Err(e) => {
+                        error!("{}", e.message);
+                        previous.quantity
+                    }
+                };
+
+                let current = WatchedListing::new(&event.listing, quantity);
+                if let Some(diff) = current.diff(&previous) {
+                    yield diff;
+                }
+                previous = current;
+            }
+        })
     }
 }
 
@@ -24,14 +164,13 @@ impl ListingChanged {
         self.mutation_type
     }
 
-    async fn id(&self) -> String {
-        self.id.to_string()
+    /// Id of the listing that changed
+    async fn id(&self) -> Uuid {
+        self.listing.id
     }
 
-    async fn listing(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Listing>> {
-        let database = extract_db(ctx)?;
-        let listing = database.get_listing_by_id(&self.id).await?;
-
-        Ok(listing)
+    /// The listing as it is after the change
+    async fn listing(&self) -> &Listing {
+        &self.listing
     }
 }
@@ -1,13 +1,32 @@
+pub(crate) mod hub;
 pub(crate) mod listing;
-use api_core::reexports::uuid::Uuid;
+
+use api_core::{Listing, ListingEvent};
+
+use crate::Database;
 
 use super::mutation::MutationType;
 
-#[derive(async_graphql::MergedSubscription, Default)]
-pub struct Subscription(listing::ListingSubscription);
+#[derive(async_graphql::MergedSubscription)]
+pub struct Subscription<D: Database>(listing::ListingSubscription<D>);
+
+impl<D: Database> Default for Subscription<D> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
 
 #[derive(Debug, Clone)]
 pub(crate) struct ListingChanged {
     pub mutation_type: MutationType,
-    pub id: Uuid,
+    pub listing: Listing,
+}
+
+impl From<ListingEvent> for ListingChanged {
+    fn from(event: ListingEvent) -> Self {
+        Self {
+            mutation_type: event.action.into(),
+            listing: event.listing,
+        }
+    }
 }
@@ -1,13 +1,60 @@
+use std::{num::NonZeroUsize, sync::Arc};
+
+use api_core::api::{
+    ListingEdgeSource, ManageWebhooks, MutateListings, QueryListingCondition, QueryListings,
+    SubscribeListings,
+};
 use api_database::Client;
 use async_graphql::{extensions::ExtensionFactory, Schema, SchemaBuilder};
 use thiserror::Error;
 use tracing::{info, instrument, trace};
 
-use self::graphql::{mutation::Mutation, query::Query, subscription::Subscription};
-
+use self::{
+    auth::{RoleCache, SharedRoleResolver},
+    graphql::{
+        loader::{data_loader, ListingLoader, RelationLoader},
+        mutation::Mutation,
+        query::Query,
+        subscription::{hub::ListingHub, Subscription},
+    },
+};
+
+pub mod auth;
 pub mod graphql;
 
+#[cfg(feature = "in-memory")]
+pub use api_core::memory::InMemoryStore;
 pub use api_database::S3Config;
+pub use auth::{Identity, Role, RoleResolver};
+
+/// A storage backend the GraphQL schema can be built over
+pub trait Database:
+    QueryListings
+    + QueryListingCondition
+    + MutateListings
+    + SubscribeListings
+    + ManageWebhooks
+    + Clone
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<T> Database for T where
+    T: QueryListings
+        + QueryListingCondition
+        + MutateListings
+        + SubscribeListings
+        + ManageWebhooks
+        + Clone
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+pub type ApiSchema<D = Client> = Schema<Query<D>, Mutation<D>, Subscription<D>>;
 
 #[derive(Debug, Clone, Copy)]
 pub struct DatabaseCredentials<'a> {
@@ -24,6 +71,9 @@ pub struct RedisConfig<'a> {
     pub clustered: bool,
     pub pool_size: u16,
     pub ttl: u64,
+    /// How many entries to keep in process ahead of redis, if any
+    pub local_cache_size: Option<NonZeroUsize>,
+    pub local_cache_ttl: u64,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -32,8 +82,9 @@ pub struct Apis<'a> {
     pub categories: &'a str,
 }
 
-pub struct ApiSchemaBuilder {
-    builder: SchemaBuilder<Query, Mutation, Subscription>,
+pub struct ApiSchemaBuilder<D: Database = Client> {
+    builder: SchemaBuilder<Query<D>, Mutation<D>, Subscription<D>>,
+    database: D,
 }
 
 #[derive(Error, Debug)]
@@ -42,7 +93,7 @@ pub enum SchemaError {
     DatabaseError(#[from] api_database::ClientError),
 }
 
-impl ApiSchemaBuilder {
+impl ApiSchemaBuilder<Client> {
     #[instrument(skip_all, fields(db.url = %database.db_dsn), name = "schema.init")]
     pub async fn new(
         database: DatabaseCredentials<'_>,
@@ -66,27 +117,62 @@ impl ApiSchemaBuilder {
         .await?;
 
         if let Some((host, api_key)) = meilisearch {
-            db_client.with_meilisearch(host, api_key);
+            db_client.with_meilisearch(host, api_key).await?;
         }
 
         if let Some(redis) = redis {
             db_client
                 .with_redis(redis.redis_dsn, redis.clustered, redis.pool_size, redis.ttl)
                 .await;
+
+            if let Some(size) = redis.local_cache_size {
+                db_client.with_local_cache(size, redis.local_cache_ttl);
+            }
         }
 
         info!("database database client created");
 
-        let builder = Self {
+        Ok(Self::with_database(db_client.clone()).with_role_resolver(db_client))
+    }
+}
+
+impl<D: Database> ApiSchemaBuilder<D> {
+    /// Builds a federated schema, so it can be composed into a supergraph. Lookups of listings
+    /// and their relations are batched across the resolvers running at the same time
+    #[instrument(skip_all, name = "schema.init")]
+    pub fn with_database(database: D) -> Self {
+        trace!("attaching database to schema");
+        Self {
             builder: Schema::build(
                 Query::default(),
                 Mutation::default(),
                 Subscription::default(),
             )
-            .data(db_client),
-        };
+            .enable_federation()
+            .data(database.clone())
+            .data(data_loader(ListingLoader(database.clone())))
+            .data(ListingHub::new(database.clone()))
+            .data::<Arc<dyn ListingEdgeSource>>(Arc::new(RelationLoader::new(database.clone()))),
+            database,
+        }
+    }
 
-        Ok(builder)
+    /// The storage backend the schema is built over, for work that runs outside of requests
+    pub fn database(&self) -> &D {
+        &self.database
+    }
+
+    /// Resolves the roles of callers whose token does not carry any, once per request
+    #[instrument(skip_all, name = "schema.roles")]
+    pub fn with_role_resolver(self, resolver: impl RoleResolver + 'static) -> Self {
+        trace!("attaching role resolver to schema");
+        Self {
+            builder: self
+                .builder
+                .data::<SharedRoleResolver>(Arc::new(resolver))
+                .extension(RoleCache),
+            ..self
+        }
     }
 
     #[instrument(skip(self, extension), name = "schema.ext")]
@@ -94,11 +180,12 @@ impl ApiSchemaBuilder {
         trace!("attaching extension to schema");
         Self {
             builder: self.builder.extension(extension),
+            ..self
         }
     }
 
     #[instrument(skip(self), name = "schema.build")]
-    pub fn build(self) -> Schema<Query, Mutation, Subscription> {
+    pub fn build(self) -> ApiSchema<D> {
         trace!("building schema");
         self.builder.finish()
     }
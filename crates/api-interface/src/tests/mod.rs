@@ -2,10 +2,13 @@ use std::sync::Arc;
 
 use async_graphql::{
     extensions::{Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest},
-    Request, ServerResult,
+    Request, Response, ServerResult, Value,
 };
 
-use crate::{ApiSchemaBuilder, Apis, DatabaseCredentials};
+use api_core::{memory::InMemoryStore, reexports::uuid::Uuid, Listing, ListingStatus};
+use time::OffsetDateTime;
+
+use crate::{ApiSchema, ApiSchemaBuilder, Apis, DatabaseCredentials, Identity, Role};
 use async_trait::async_trait;
 
 mod mutation;
@@ -34,11 +37,7 @@ impl Extension for TestExtension {
     }
 }
 
-async fn init_schema() -> async_graphql::Schema<
-    crate::graphql::query::Query,
-    crate::graphql::mutation::Mutation,
-    crate::graphql::subscription::Subscription,
-> {
+async fn init_schema() -> ApiSchema {
     dotenvy::dotenv().ok();
     let db_host = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL");
     let db_host = db_host.replace("http://", "");
@@ -79,3 +78,41 @@ async fn init_schema() -> async_graphql::Schema<
     .with_extension(DummyExtension)
     .build()
 }
+
+fn init_memory_schema() -> (InMemoryStore, ApiSchema<InMemoryStore>) {
+    let database = InMemoryStore::new();
+    let schema = ApiSchemaBuilder::with_database(database.clone())
+        .with_extension(DummyExtension)
+        .build();
+
+    (database, schema)
+}
+
+fn sample_listing(title: &str) -> Listing {
+    Listing {
+        id: Uuid::nil(),
+        title: title.to_owned(),
+        description: String::from("Description"),
+        price: 10.into(),
+        image_url: String::from("https://dummyimage.com/420x260"),
+        other_images: vec![],
+        status: ListingStatus::Active,
+        negotiable: false,
+        created: OffsetDateTime::now_utc(),
+        expires: None,
+        updated: OffsetDateTime::now_utc(),
+        deleted: None,
+    }
+}
+
+fn as_user(query: impl Into<String>, user_id: Uuid, roles: &[Role]) -> Request {
+    Request::new(query).data(Identity::new(user_id).with_roles(roles.iter().copied()))
+}
+
+/// The `code` extension of the first error in a response
+fn error_code(res: &Response) -> Option<&str> {
+    match res.errors.first()?.extensions.as_ref()?.get("code")? {
+        Value::String(code) => Some(code),
+        _ => None,
+    }
+}
@@ -98,3 +98,382 @@ async fn gql_mutation() {
     let id_3 = execute_mutation(&delete_mutation, &schema, "deleteListing").await;
     assert_eq!(&id, &id_3);
 } */
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use api_core::{
+    api::{CoreError, MutateListings},
+    memory::InMemoryStore,
+    reexports::uuid::Uuid,
+};
+use async_trait::async_trait;
+
+use super::error_code;
+use crate::{ApiSchemaBuilder, Identity, Role, RoleResolver};
+
+#[tokio::test]
+async fn gql_mutation_in_memory() {
+    let (_database, schema) = super::init_memory_schema();
+
+    let create = r#"
+           mutation {
+             createListing(
+               input: {
+                 title: "Title",
+                 description: "Description",
+                 price: 10,
+                 imageUrl: "https://dummyimage.com/420x260",
+                 status: DRAFT
+               },
+               metadata: {
+                 categoryId: "018d930d-073c-73c2-b9d6-24f1461c18d3",
+                 conditionId: "018d930d-073c-73c2-b9d6-24f1461c18d3"
+               }
+             ) {
+               id
+             }
+           }
+           "#;
+
+    let res = schema.execute(create).await;
+    assert_eq!(error_code(&res), Some("UNAUTHENTICATED"));
+
+    let res = schema
+        .execute(super::as_user(create, Uuid::now_v7(), &[Role::Buyer]))
+        .await;
+    assert_eq!(error_code(&res), Some("FORBIDDEN"));
+
+    let seller = Uuid::now_v7();
+    let res = schema
+        .execute(super::as_user(create, seller, &[Role::Seller]))
+        .await;
+    assert!(res.errors.is_empty());
+
+    // drafts are hidden from everyone but their seller and admins
+    let id = res.data.into_json().unwrap()["createListing"]["id"].clone();
+    let query = format!(r#"query {{ listingById(id: {id}) {{ title }} }}"#);
+    let res = schema.execute(query.as_str()).await;
+    assert_eq!(
+        res.data.into_json().unwrap(),
+        serde_json::json!({ "listingById": null })
+    );
+
+    let res = schema.execute(super::as_user(query, seller, &[])).await;
+
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap(),
+        serde_json::json!({ "listingById": { "title": "Title" } })
+    );
+}
+
+#[tokio::test]
+async fn gql_transition_in_memory() {
+    let (database, schema) = super::init_memory_schema();
+    let user = Uuid::now_v7();
+    let listing = database
+        .create_listing(
+            &super::sample_listing("Title"),
+            &user,
+            &Uuid::now_v7(),
+            &Uuid::now_v7(),
+            1,
+        )
+        .await
+        .unwrap();
+
+    let transition = |status: &str| {
+        let query = format!(
+            r#"mutation {{
+                transitionListing(id: "{}", status: {status}) {{ status }}
+            }}"#,
+            listing.id
+        );
+        super::as_user(query, user, &[])
+    };
+
+    let res = schema.execute(transition("DRAFT")).await;
+    assert!(!res.errors.is_empty()); // active listings cannot go back to drafts
+
+    let res = schema.execute(transition("RESERVED")).await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap(),
+        serde_json::json!({ "transitionListing": { "status": "RESERVED" } })
+    );
+
+    // removing a listing is left to moderators, who may remove any listing
+    let res = schema.execute(transition("REMOVED")).await;
+    assert_eq!(error_code(&res), Some("FORBIDDEN"));
+
+    let remove = format!(
+        r#"mutation {{
+            transitionListing(id: "{}", status: REMOVED) {{ status }}
+        }}"#,
+        listing.id
+    );
+    let res = schema
+        .execute(super::as_user(remove, Uuid::now_v7(), &[Role::Moderator]))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap(),
+        serde_json::json!({ "transitionListing": { "status": "REMOVED" } })
+    );
+}
+
+#[tokio::test]
+async fn gql_delete_restore_in_memory() {
+    let (database, schema) = super::init_memory_schema();
+    let user = Uuid::now_v7();
+    let listing = database
+        .create_listing(
+            &super::sample_listing("Title"),
+            &user,
+            &Uuid::now_v7(),
+            &Uuid::now_v7(),
+            1,
+        )
+        .await
+        .unwrap();
+    let id = listing.id;
+
+    let res = schema
+        .execute(super::as_user(
+            format!(r#"mutation {{ deleteListing(id: "{id}") {{ title }} }}"#),
+            user,
+            &[],
+        ))
+        .await;
+    assert!(res.errors.is_empty());
+
+    let res = schema
+        .execute(format!(
+            r#"query {{ listingById(id: "{id}") {{ title }} }}"#
+        ))
+        .await;
+    assert_eq!(
+        res.data.into_json().unwrap(),
+        serde_json::json!({ "listingById": null })
+    );
+
+    let res = schema
+        .execute(super::as_user(
+            format!(r#"mutation {{ restoreListing(id: "{id}") {{ title }} }}"#),
+            user,
+            &[],
+        ))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap(),
+        serde_json::json!({ "restoreListing": { "title": "Title" } })
+    );
+}
+
+#[tokio::test]
+async fn gql_forbidden_in_memory() {
+    let (database, schema) = super::init_memory_schema();
+    let listing = database
+        .create_listing(
+            &super::sample_listing("Title"),
+            &Uuid::now_v7(),
+            &Uuid::now_v7(),
+            &Uuid::now_v7(),
+            1,
+        )
+        .await
+        .unwrap();
+
+    let res = schema
+        .execute(super::as_user(
+            format!(
+                r#"mutation {{ deleteListing(id: "{}") {{ title }} }}"#,
+                listing.id
+            ),
+            Uuid::now_v7(),
+            &[],
+        ))
+        .await;
+
+    assert_eq!(error_code(&res), Some("FORBIDDEN"));
+}
+
+#[tokio::test]
+async fn gql_moderation_in_memory() {
+    let (database, schema) = super::init_memory_schema();
+    let listing = database
+        .create_listing(
+            &super::sample_listing("Title"),
+            &Uuid::now_v7(),
+            &Uuid::now_v7(),
+            &Uuid::now_v7(),
+            1,
+        )
+        .await
+        .unwrap();
+
+    let delete = |roles: &[Role]| {
+        super::as_user(
+            format!(
+                r#"mutation {{ deleteListing(id: "{}") {{ title }} }}"#,
+                listing.id
+            ),
+            Uuid::now_v7(),
+            roles,
+        )
+    };
+
+    let res = schema.execute(delete(&[Role::Seller])).await;
+    assert!(!res.errors.is_empty());
+
+    let res = schema.execute(delete(&[Role::Moderator])).await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap(),
+        serde_json::json!({ "deleteListing": { "title": "Title" } })
+    );
+}
+
+#[tokio::test]
+async fn gql_resolved_roles_in_memory() {
+    struct Sellers(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl RoleResolver for Sellers {
+        async fn roles(&self, _user_id: &Uuid) -> Result<Vec<Role>, CoreError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![Role::Seller])
+        }
+    }
+
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let schema = ApiSchemaBuilder::with_database(InMemoryStore::new())
+        .with_role_resolver(Sellers(Arc::clone(&lookups)))
+        .build();
+
+    let create = r#"
+             createListing(
+               input: {
+                 title: "Title",
+                 description: "Description",
+                 price: 10,
+                 imageUrl: "https://dummyimage.com/420x260"
+               },
+               metadata: {
+                 categoryId: "018d930d-073c-73c2-b9d6-24f1461c18d3",
+                 conditionId: "018d930d-073c-73c2-b9d6-24f1461c18d3"
+               }
+             ) {
+               title
+             }
+           "#;
+
+    let res = schema
+        .execute(
+            async_graphql::Request::new(format!("mutation {{ first: {create} second: {create} }}"))
+                .data(Identity::new(Uuid::now_v7())),
+        )
+        .await;
+    assert!(res.errors.is_empty());
+    // both guarded fields share the roles looked up for the request
+    assert_eq!(lookups.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn gql_webhooks_in_memory() {
+    let (_database, schema) = super::init_memory_schema();
+    let admin = Uuid::now_v7();
+
+    let create = |url: &str, roles: &[Role]| {
+        super::as_user(
+            format!(
+                r#"mutation {{
+                    createWebhook(
+                        url: "{url}",
+                        secret: "a-secret-of-some-length",
+                        events: [LISTING_CREATED, LISTING_STATUS_CHANGED]
+                    ) {{ id url events }}
+                }}"#
+            ),
+            admin,
+            roles,
+        )
+    };
+
+    let res = schema
+        .execute(create("https://example.com/hooks", &[Role::Seller]))
+        .await;
+    assert!(!res.errors.is_empty());
+
+    for url in [
+        "ftp://example.com/hooks",
+        "http://example.com/hooks",
+        "https://localhost:8080/hooks",
+        "https://127.0.0.1/hooks",
+        "https://10.0.0.8/hooks",
+        "https://169.254.169.254/latest",
+        "https://[::1]/hooks",
+        "https://[fd00::1]:443/hooks",
+        "https://[::ffff:192.168.0.1]/hooks",
+        "https://user@example.com/hooks",
+        "https://service.internal/hooks",
+        // shorthand spellings of the loopback address
+        "https://2130706433/hooks",
+        "https://127.1/hooks",
+        "https://0x7f.0.0.1/hooks",
+        "https://0177.0.0.1/hooks",
+        "https://:pass@example.com/hooks",
+        "not a url",
+    ] {
+        let res = schema.execute(create(url, &[Role::Admin])).await;
+        assert!(!res.errors.is_empty(), "{url} was accepted");
+    }
+
+    let res = schema
+        .execute(create("https://example.com/hooks", &[Role::Admin]))
+        .await;
+    assert!(res.errors.is_empty());
+    let webhook = res.data.into_json().unwrap()["createWebhook"].clone();
+    assert_eq!(
+        webhook["events"],
+        serde_json::json!(["LISTING_CREATED", "LISTING_STATUS_CHANGED"])
+    );
+
+    let res = schema
+        .execute(super::as_user(
+            format!(
+                r#"query {{
+                    webhooks {{ url }}
+                    webhookDeliveries(webhookId: {}) {{ status }}
+                }}"#,
+                webhook["id"]
+            ),
+            admin,
+            &[Role::Admin],
+        ))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap(),
+        serde_json::json!({
+            "webhooks": [{ "url": "https://example.com/hooks" }],
+            "webhookDeliveries": []
+        })
+    );
+
+    let res = schema
+        .execute(super::as_user(
+            format!(
+                r#"mutation {{ deleteWebhook(id: {}) {{ url }} }}"#,
+                webhook["id"]
+            ),
+            admin,
+            &[Role::Admin],
+        ))
+        .await;
+    assert!(res.errors.is_empty());
+}
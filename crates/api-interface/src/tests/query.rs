@@ -1,3 +1,12 @@
+use api_core::{
+    api::MutateListings, reexports::uuid::Uuid, GlobalId, Listing, ListingCondition, ListingStatus,
+    NodeType,
+};
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+
+use super::error_code;
+use crate::Role;
+
 #[tokio::test]
 async fn gql_query() -> Result<(), Box<dyn std::error::Error>> {
     let schema = super::init_schema().await;
@@ -80,3 +89,608 @@ async fn gql_search_ok() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn gql_query_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let id = Uuid::nil();
+    database
+        .create_listing(&super::sample_listing("Title"), &id, &id, &id, 1)
+        .await?;
+
+    let res = schema
+        .execute(
+            r#"
+           query {
+             listings(first: 2) {
+               edges{
+                 node{
+                   title
+                 }
+               },
+               totalCount
+             }
+           }
+           "#,
+        )
+        .await;
+
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({
+            "listings": {
+                "edges": [{ "node": { "title": "Title" } }],
+                "totalCount": 1
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_query_status_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let id = Uuid::nil();
+    let draft = Listing {
+        status: ListingStatus::Draft,
+        ..super::sample_listing("Draft")
+    };
+    database.create_listing(&draft, &id, &id, &id, 1).await?;
+    database
+        .create_listing(&super::sample_listing("Active"), &id, &id, &id, 1)
+        .await?;
+
+    let query = |status: &str| {
+        format!(r#"query {{ listings(first: 2{status}) {{ edges {{ node {{ title }} }} }} }}"#)
+    };
+
+    let res = schema.execute(query("")).await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({ "listings": { "edges": [{ "node": { "title": "Active" } }] } })
+    );
+
+    let res = schema
+        .execute(super::as_user(
+            query(", status: DRAFT"),
+            id,
+            &[Role::Seller],
+        ))
+        .await;
+    assert_eq!(error_code(&res), Some("FORBIDDEN"));
+
+    let res = schema
+        .execute(super::as_user(query(", status: DRAFT"), id, &[Role::Admin]))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({ "listings": { "edges": [{ "node": { "title": "Draft" } }] } })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_query_user_tags_price_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let (seller, other) = (Uuid::now_v7(), Uuid::now_v7());
+    let cheap = database
+        .create_listing(
+            &super::sample_listing("Cheap"),
+            &seller,
+            &seller,
+            &seller,
+            1,
+        )
+        .await?;
+    let draft = Listing {
+        status: ListingStatus::Draft,
+        price: 500.into(),
+        ..super::sample_listing("Draft")
+    };
+    database
+        .create_listing(&draft, &seller, &seller, &seller, 1)
+        .await?;
+    let pricey = Listing {
+        price: 750.into(),
+        ..super::sample_listing("Pricey")
+    };
+    database
+        .create_listing(&pricey, &other, &other, &other, 1)
+        .await?;
+
+    let tag = Uuid::now_v7();
+    database.tag_listing(&cheap.id, &[tag])?;
+
+    let titles = |query: &str, field: &str, res: async_graphql::Response| {
+        assert!(res.errors.is_empty(), "{query}: {:?}", res.errors);
+        let data = res.data.into_json().unwrap();
+        data[field]["edges"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|edge| edge["node"]["title"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    let query = format!(
+        r#"query {{ listingsByUser(userId: "{seller}", first: 10) {{ edges {{ node {{ title }} }} }} }}"#
+    );
+    let res = schema.execute(query.as_str()).await;
+    assert_eq!(titles(&query, "listingsByUser", res), vec!["Cheap"]);
+
+    // sellers see their own drafts, other callers do not
+    let query = format!(
+        r#"query {{ listingsByUser(userId: "{seller}", status: DRAFT, first: 10) {{ edges {{ node {{ title }} }} }} }}"#
+    );
+    let res = schema
+        .execute(super::as_user(query.as_str(), seller, &[Role::Seller]))
+        .await;
+    assert_eq!(titles(&query, "listingsByUser", res), vec!["Draft"]);
+    let res = schema
+        .execute(super::as_user(query.as_str(), other, &[Role::Seller]))
+        .await;
+    assert!(!res.errors.is_empty());
+
+    let query = format!(
+        r#"query {{ listingsWithTags(tags: ["{tag}"], first: 10) {{ edges {{ node {{ title }} }} }} }}"#
+    );
+    let res = schema.execute(query.as_str()).await;
+    assert_eq!(titles(&query, "listingsWithTags", res), vec!["Cheap"]);
+
+    let query = r#"query { listingsInPriceRange(min: "100", max: "1000.50", first: 10) { edges { node { title } } } }"#;
+    let res = schema.execute(query).await;
+    assert_eq!(titles(query, "listingsInPriceRange", res), vec!["Pricey"]);
+
+    let res = schema
+        .execute(r#"query { listingsInPriceRange(min: "10", max: "1", first: 10) { edges { node { title } } } }"#)
+        .await;
+    assert!(!res.errors.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_query_filter_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let (user, phones) = (Uuid::now_v7(), Uuid::now_v7());
+    for (title, price, negotiable, category) in [
+        ("Cheap phone", 150, true, phones),
+        ("Firm phone", 120, false, phones),
+        ("Laptop", 180, true, Uuid::now_v7()),
+    ] {
+        let listing = Listing {
+            price: price.into(),
+            negotiable,
+            ..super::sample_listing(title)
+        };
+        database
+            .create_listing(&listing, &user, &category, &user, 1)
+            .await?;
+    }
+
+    let res = schema
+        .execute(format!(
+            r#"query {{
+                 listings(
+                   filter: {{ categoryId: "{phones}", maxPrice: "200", negotiable: true }},
+                   orderBy: PRICE_ASC,
+                   first: 10
+                 ) {{ edges {{ node {{ title }} }} }}
+               }}"#
+        ))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({ "listings": { "edges": [{ "node": { "title": "Cheap phone" } }] } })
+    );
+
+    let res = schema
+        .execute(
+            r#"query { listings(orderBy: PRICE_DESC, first: 10) { edges { node { title } } } }"#,
+        )
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({ "listings": { "edges": [
+            { "node": { "title": "Laptop" } },
+            { "node": { "title": "Cheap phone" } },
+            { "node": { "title": "Firm phone" } }
+        ] } })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_query_cursor_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let id = Uuid::nil();
+    let create = |title: &'static str, price: i64| {
+        let database = database.clone();
+        async move {
+            let listing = Listing {
+                price: price.into(),
+                ..super::sample_listing(title)
+            };
+            database.create_listing(&listing, &id, &id, &id, 1).await
+        }
+    };
+    for (title, price) in [("One", 100), ("Two", 200), ("Three", 300)] {
+        create(title, price).await?;
+    }
+
+    let page = |args: String| {
+        let schema = schema.clone();
+        async move {
+            let res = schema
+                .execute(format!(
+                    r#"query {{
+                         listings(orderBy: PRICE_ASC, {args}) {{
+                           edges {{ cursor node {{ title }} }}
+                           pageInfo {{ hasNextPage hasPreviousPage }}
+                           totalCount
+                         }}
+                       }}"#
+                ))
+                .await;
+            assert!(res.errors.is_empty(), "{:?}", res.errors);
+            res.data.into_json().unwrap()["listings"].clone()
+        }
+    };
+    let titles = |page: &serde_json::Value| {
+        page["edges"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|edge| edge["node"]["title"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    let first = page(String::from("first: 1")).await;
+    assert_eq!(titles(&first), vec!["One"]);
+    assert_eq!(first["pageInfo"]["hasNextPage"], true);
+    let cursor = first["edges"][0]["cursor"].as_str().unwrap().to_owned();
+
+    // a listing sorted before the cursor neither repeats nor skips anything
+    create("Zero", 50).await?;
+    let next = page(format!(r#"first: 2, after: "{cursor}""#)).await;
+    assert_eq!(titles(&next), vec!["Two", "Three"]);
+    assert_eq!(next["pageInfo"]["hasNextPage"], false);
+    assert_eq!(next["pageInfo"]["hasPreviousPage"], true);
+    assert_eq!(next["totalCount"], 4);
+
+    let back = page(format!(r#"last: 2, before: "{cursor}""#)).await;
+    assert_eq!(titles(&back), vec!["Zero"]);
+    assert_eq!(back["pageInfo"]["hasPreviousPage"], false);
+    assert_eq!(back["pageInfo"]["hasNextPage"], true);
+
+    // cursors only work with the order they were made for, and in the current version
+    let stale = BASE64_URL_SAFE_NO_PAD.encode(format!("v0:price_asc:{id}:100"));
+    for after in [String::from("Q3Vyc29yOjA"), stale] {
+        let res = schema
+            .execute(format!(
+                r#"query {{ listings(orderBy: PRICE_ASC, first: 1, after: "{after}") {{ totalCount }} }}"#
+            ))
+            .await;
+        assert!(!res.errors.is_empty());
+    }
+    let res = schema
+        .execute(format!(
+            r#"query {{ listings(orderBy: CREATED_ASC, first: 1, after: "{cursor}") {{ totalCount }} }}"#
+        ))
+        .await;
+    assert!(!res.errors.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_search_pages_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let id = Uuid::nil();
+    for _ in 0..102 {
+        database
+            .create_listing(&super::sample_listing("Laptop"), &id, &id, &id, 1)
+            .await?;
+    }
+    let draft = api_core::Listing {
+        status: ListingStatus::Draft,
+        ..super::sample_listing("Laptop")
+    };
+    database.create_listing(&draft, &id, &id, &id, 1).await?;
+
+    let search = |args: &str| {
+        let schema = schema.clone();
+        let args = args.to_owned();
+        async move {
+            let res = schema
+                .execute(format!(
+                    r#"query {{
+                         search(query: "laptop", {args}) {{
+                           edges {{ cursor }}
+                           pageInfo {{ hasNextPage hasPreviousPage }}
+                           totalCount
+                         }}
+                       }}"#
+                ))
+                .await;
+            assert!(res.errors.is_empty(), "{:?}", res.errors);
+            res.data.into_json().unwrap()["search"].clone()
+        }
+    };
+
+    let first = search("first: 100").await;
+    assert_eq!(first["edges"].as_array().unwrap().len(), 100);
+    assert_eq!(first["pageInfo"]["hasNextPage"], true);
+    assert_eq!(first["totalCount"], 102);
+    let cursor = first["edges"][99]["cursor"].as_str().unwrap().to_owned();
+
+    // drafts are left out of the page and of the total
+    let rest = search(&format!(r#"first: 100, after: "{cursor}""#)).await;
+    assert_eq!(rest["edges"].as_array().unwrap().len(), 2);
+    assert_eq!(rest["pageInfo"]["hasNextPage"], false);
+    assert_eq!(rest["pageInfo"]["hasPreviousPage"], true);
+
+    let last = search("last: 2").await;
+    assert_eq!(last["edges"], rest["edges"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_node_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let id = Uuid::nil();
+    let listing = database
+        .create_listing(&super::sample_listing("Title"), &id, &id, &id, 1)
+        .await?;
+
+    let res = schema
+        .execute(r#"query { listings(first: 1) { edges { node { id globalId } } } }"#)
+        .await;
+    assert!(res.errors.is_empty());
+    let node = res.data.into_json()?["listings"]["edges"][0]["node"].clone();
+    let global_id = GlobalId::new(NodeType::Listing, listing.id).to_string();
+    assert_eq!(node["id"], listing.id.to_string());
+    assert_eq!(node["globalId"], global_id.as_str());
+
+    let missing = GlobalId::new(NodeType::Listing, Uuid::now_v7());
+    let res = schema
+        .execute(format!(
+            r#"query {{
+                 node(id: "{global_id}") {{ globalId ... on Listing {{ title }} }}
+                 nodes(ids: ["{missing}", "{global_id}"]) {{ __typename }}
+               }}"#
+        ))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({
+            "node": { "globalId": global_id, "title": "Title" },
+            "nodes": [null, { "__typename": "Listing" }]
+        })
+    );
+
+    // raw ids are not global ids
+    let res = schema
+        .execute(format!(
+            r#"query {{ node(id: "{}") {{ globalId }} }}"#,
+            listing.id
+        ))
+        .await;
+    assert!(!res.errors.is_empty());
+
+    // drafts are only found by their seller and admins, like through listingById
+    let seller = Uuid::now_v7();
+    let draft = Listing {
+        status: ListingStatus::Draft,
+        ..super::sample_listing("Draft")
+    };
+    let draft = database
+        .create_listing(&draft, &seller, &seller, &seller, 1)
+        .await?;
+    let draft_id = GlobalId::new(NodeType::Listing, draft.id);
+    let query = format!(
+        r#"query {{
+             node(id: "{draft_id}") {{ globalId }}
+             nodes(ids: ["{draft_id}"]) {{ globalId }}
+             listingById(id: "{}") {{ title }}
+           }}"#,
+        draft.id
+    );
+    for (request, visible) in [
+        (async_graphql::Request::new(query.clone()), false),
+        (super::as_user(query.clone(), Uuid::now_v7(), &[]), false),
+        (super::as_user(query.clone(), seller, &[]), true),
+        (
+            super::as_user(query.clone(), Uuid::now_v7(), &[Role::Admin]),
+            true,
+        ),
+    ] {
+        let res = schema.execute(request).await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        let data = res.data.into_json()?;
+        assert_eq!(!data["node"].is_null(), visible);
+        assert_eq!(!data["nodes"][0].is_null(), visible);
+        assert_eq!(!data["listingById"].is_null(), visible);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_federation_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let (seller, category) = (Uuid::now_v7(), Uuid::now_v7());
+    let listing = database
+        .create_listing(
+            &super::sample_listing("Title"),
+            &seller,
+            &category,
+            &seller,
+            1,
+        )
+        .await?;
+
+    let res = schema.execute("query { _service { sdl } }").await;
+    assert!(res.errors.is_empty());
+    let sdl = res.data.into_json()?["_service"]["sdl"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    assert!(sdl.contains("https://specs.apollo.dev/federation/v2"));
+    assert!(sdl.contains(r#"type Listing implements Node @key(fields: "id")"#));
+    assert!(sdl.contains(r#"type User @key(fields: "id", resolvable: false)"#));
+    assert!(sdl.contains(r#"type Category @key(fields: "id", resolvable: false)"#));
+
+    let id = listing.id;
+    let res = schema
+        .execute(format!(
+            r#"query {{
+                 _entities(representations: [{{ __typename: "Listing", id: "{id}" }}]) {{
+                   ... on Listing {{ title seller {{ id }} category {{ id }} }}
+                 }}
+               }}"#
+        ))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({ "_entities": [{
+            "title": "Title",
+            "seller": { "id": seller },
+            "category": { "id": category }
+        }] })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_listing_relations_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut database, schema) = super::init_memory_schema();
+
+    let condition = ListingCondition {
+        id: Uuid::now_v7(),
+        condition: String::from("Used"),
+    };
+    database.with_conditions([condition.clone()]);
+
+    let (seller, category) = (Uuid::now_v7(), Uuid::now_v7());
+    database
+        .create_listing(
+            &super::sample_listing("Title"),
+            &seller,
+            &category,
+            &condition.id,
+            3,
+        )
+        .await?;
+
+    let res = schema
+        .execute(
+            r#"query {
+                 listings(first: 1) {
+                   edges { node { sellerId categoryId quantity condition { condition } } }
+                 }
+               }"#,
+        )
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({ "listings": { "edges": [{ "node": {
+            "sellerId": seller,
+            "categoryId": category,
+            "quantity": 3,
+            "condition": { "condition": "Used" }
+        } }] } })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_batched_lookups_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+
+    let mut listings = Vec::new();
+    for quantity in 1..=3 {
+        let seller = Uuid::now_v7();
+        let listing = database
+            .create_listing(
+                &super::sample_listing("Title"),
+                &seller,
+                &seller,
+                &seller,
+                quantity,
+            )
+            .await?;
+        listings.push((listing.id, seller, quantity));
+    }
+
+    // relations loaded in one batch still end up on the listing they belong to
+    let res = schema
+        .execute(
+            r#"query {
+                 listings(first: 3) { edges { node { id sellerId quantity } } }
+               }"#,
+        )
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    let data = res.data.into_json()?;
+    let nodes = data["listings"]["edges"].as_array().unwrap();
+    assert_eq!(nodes.len(), 3);
+    for node in nodes {
+        let (_, seller, quantity) = listings
+            .iter()
+            .find(|(id, ..)| node["node"]["id"] == id.to_string())
+            .unwrap();
+        assert_eq!(node["node"]["sellerId"], seller.to_string());
+        assert_eq!(node["node"]["quantity"], *quantity);
+    }
+
+    let (first, second) = (listings[0].0, listings[1].0);
+    let first_id = GlobalId::new(NodeType::Listing, first);
+    let second_id = GlobalId::new(NodeType::Listing, second);
+    let res = schema
+        .execute(format!(
+            r#"query {{
+                 nodes(ids: ["{second_id}", "{first_id}", "{second_id}"]) {{
+                   ... on Listing {{ id }}
+                 }}
+                 listingById(id: "{first}") {{ id }}
+               }}"#
+        ))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({
+            "nodes": [
+                { "id": second },
+                { "id": first },
+                { "id": second }
+            ],
+            "listingById": { "id": first }
+        })
+    );
+
+    Ok(())
+}
@@ -1,3 +1,11 @@
+use api_core::{api::MutateListings, memory::InMemoryStore, reexports::uuid::Uuid, ListingStatus};
+use futures_util::{FutureExt, StreamExt};
+
+use crate::{
+    graphql::subscription::hub::{ListingHub, SUBSCRIBER_BUFFER},
+    Role,
+};
+
 #[tokio::test]
 async fn gql_subscription() -> Result<(), Box<dyn std::error::Error>> {
     let schema = super::init_schema().await;
@@ -18,3 +26,246 @@ async fn gql_subscription() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn gql_subscription_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+    let (seller, other) = (Uuid::now_v7(), Uuid::now_v7());
+
+    let query = format!(
+        r#"subscription {{
+             listings(mutationTypes: [CREATED, DELETED], sellerId: "{seller}", maxPrice: 50) {{
+               mutationType
+               listing {{ title }}
+             }}
+           }}"#
+    );
+    let mut stream = schema.execute_stream(query.as_str());
+    let mut admin_stream = schema.execute_stream(super::as_user(&query, other, &[Role::Admin]));
+    // the first poll subscribes, after which the shared live query opens in the background
+    assert!(stream.next().now_or_never().is_none());
+    assert!(admin_stream.next().now_or_never().is_none());
+    tokio::task::yield_now().await;
+
+    // drafts are only sent to admins
+    let mut draft = super::sample_listing("Draft");
+    draft.status = ListingStatus::Draft;
+    database
+        .create_listing(&draft, &seller, &seller, &seller, 1)
+        .await?;
+
+    let mut expensive = super::sample_listing("Expensive");
+    expensive.price = 100.into();
+    database
+        .create_listing(&expensive, &seller, &seller, &seller, 1)
+        .await?;
+    database
+        .create_listing(&super::sample_listing("Other"), &other, &other, &other, 1)
+        .await?;
+    let listing = database
+        .create_listing(
+            &super::sample_listing("Title"),
+            &seller,
+            &seller,
+            &seller,
+            1,
+        )
+        .await?;
+    database
+        .update_listing(&listing.id, &listing, &seller, &seller, &seller, 2)
+        .await?;
+    database.delete_listing(&listing.id, &seller).await?;
+
+    for mutation_type in ["CREATED", "DELETED"] {
+        let res = stream.next().await.expect("a change");
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        assert_eq!(
+            res.data.into_json()?,
+            serde_json::json!({ "listings": {
+                "mutationType": mutation_type,
+                "listing": { "title": "Title" }
+            } })
+        );
+    }
+    assert!(stream.next().now_or_never().is_none());
+
+    for (mutation_type, title) in [
+        ("CREATED", "Draft"),
+        ("CREATED", "Title"),
+        ("DELETED", "Title"),
+    ] {
+        let res = admin_stream.next().await.expect("a change");
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        assert_eq!(
+            res.data.into_json()?,
+            serde_json::json!({ "listings": {
+                "mutationType": mutation_type,
+                "listing": { "title": title }
+            } })
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn gql_listing_updated_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let (database, schema) = super::init_memory_schema();
+    let seller = Uuid::now_v7();
+    let listing = database
+        .create_listing(
+            &super::sample_listing("Title"),
+            &seller,
+            &seller,
+            &seller,
+            1,
+        )
+        .await?;
+    let other = database
+        .create_listing(
+            &super::sample_listing("Other"),
+            &seller,
+            &seller,
+            &seller,
+            1,
+        )
+        .await?;
+
+    let query = format!(
+        r#"subscription {{
+             listingUpdated(id: "{}") {{
+               price {{ previous current }}
+               status {{ previous current }}
+               quantity {{ previous current }}
+             }}
+           }}"#,
+        listing.id
+    );
+    let mut stream = schema.execute_stream(async_graphql::Request::new(query.as_str()));
+    let mut seller_stream = schema.execute_stream(super::as_user(&query, seller, &[]));
+    // the first poll subscribes, after which the shared live query opens in the background
+    assert!(stream.next().now_or_never().is_none());
+    assert!(seller_stream.next().now_or_never().is_none());
+    tokio::task::yield_now().await;
+
+    // quantities are read as changes arrive, so each is awaited before making the next one
+    let mut cheaper = listing.clone();
+    cheaper.price = 5.into();
+    database
+        .update_listing(&listing.id, &cheaper, &seller, &seller, &seller, 2)
+        .await?;
+    let price = serde_json::json!({
+        "price": { "previous": "10", "current": "5" },
+        "status": null,
+        "quantity": { "previous": 1, "current": 2 }
+    });
+    for stream in [&mut stream, &mut seller_stream] {
+        let res = stream.next().await.expect("a diff");
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        assert_eq!(
+            res.data.into_json()?,
+            serde_json::json!({ "listingUpdated": price })
+        );
+    }
+
+    // neither other listings nor unwatched fields are reported
+    database
+        .update_listing(&other.id, &cheaper, &seller, &seller, &seller, 2)
+        .await?;
+    database
+        .update_listing(&listing.id, &cheaper, &seller, &seller, &seller, 3)
+        .await?;
+    let quantity = serde_json::json!({
+        "price": null,
+        "status": null,
+        "quantity": { "previous": 2, "current": 3 }
+    });
+    for stream in [&mut stream, &mut seller_stream] {
+        let res = stream.next().await.expect("a diff");
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        assert_eq!(
+            res.data.into_json()?,
+            serde_json::json!({ "listingUpdated": quantity })
+        );
+    }
+
+    cheaper.title = String::from("Renamed");
+    database
+        .update_listing(&listing.id, &cheaper, &seller, &seller, &seller, 3)
+        .await?;
+    database
+        .transition_listing(&listing.id, &seller, ListingStatus::Sold)
+        .await?;
+    database.delete_listing(&listing.id, &seller).await?;
+
+    // only the seller hears about the listing once it is no longer active
+    let res = seller_stream.next().await.expect("a diff");
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data.into_json()?,
+        serde_json::json!({ "listingUpdated": {
+            "price": null,
+            "status": { "previous": "ACTIVE", "current": "SOLD" },
+            "quantity": null
+        } })
+    );
+    // deleting the listing ends the stream
+    assert!(stream.next().await.is_none());
+    assert!(seller_stream.next().await.is_none());
+
+    let mut draft = super::sample_listing("Draft");
+    draft.status = ListingStatus::Draft;
+    let draft = database
+        .create_listing(&draft, &seller, &seller, &seller, 1)
+        .await?;
+    for id in [draft.id, Uuid::now_v7()] {
+        let res = schema
+            .execute_stream(format!(
+                r#"subscription {{ listingUpdated(id: "{id}") {{ price {{ current }} }} }}"#
+            ))
+            .next()
+            .await
+            .expect("an error");
+        assert!(!res.errors.is_empty());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn listing_hub_fan_out() -> Result<(), Box<dyn std::error::Error>> {
+    let database = InMemoryStore::new();
+    let hub = ListingHub::new(database.clone());
+    assert!(!hub.is_live());
+
+    let (mut first, second) = (hub.subscribe(), hub.subscribe());
+    assert!(hub.is_live());
+    // let the live query open
+    tokio::task::yield_now().await;
+
+    // the second subscriber never reads, so it is disconnected once its buffer is full
+    let seller = Uuid::now_v7();
+    for _ in 0..SUBSCRIBER_BUFFER + 2 {
+        let listing = database
+            .create_listing(
+                &super::sample_listing("Title"),
+                &seller,
+                &seller,
+                &seller,
+                1,
+            )
+            .await?;
+        let event = first.next().await.expect("a change");
+        assert_eq!(event.listing, listing);
+    }
+    assert_eq!(second.count().await, SUBSCRIBER_BUFFER + 1);
+    assert!(hub.is_live());
+
+    // the live query is closed with the last subscriber and opened again for the next one
+    drop(first);
+    assert!(!hub.is_live());
+    let _third = hub.subscribe();
+    assert!(hub.is_live());
+
+    Ok(())
+}
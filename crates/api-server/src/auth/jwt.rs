@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use api_interface::{Identity, Role};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::{AuthError, Authenticator};
+
+enum Keys {
+    Shared(DecodingKey),
+    /// Keys from a JWKS, matched against the `kid` of a token
+    Set(Vec<(Option<String>, DecodingKey)>),
+}
+
+/// Validates JWTs signed with a shared HS256 secret or with RS256 keys from a JWKS. The `sub`
+/// claim of a token is the id of the user making the request, and an optional `roles` claim
+/// lists the roles they hold. Roles this service does not know about are ignored
+pub struct JwtAuthenticator {
+    keys: Keys,
+    validation: Validation,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+impl JwtAuthenticator {
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self::new(
+            Keys::Shared(DecodingKey::from_secret(secret)),
+            Algorithm::HS256,
+        )
+    }
+
+    pub fn rs256(jwks: &JwkSet) -> Result<Self, AuthError> {
+        let keys = jwks
+            .keys
+            .iter()
+            .filter(|jwk| matches!(jwk.algorithm, AlgorithmParameters::RSA(_)))
+            .map(|jwk| Ok((jwk.common.key_id.clone(), DecodingKey::from_jwk(jwk)?)))
+            .collect::<Result<Vec<_>, AuthError>>()?;
+
+        if keys.is_empty() {
+            return Err(AuthError::UnknownKey);
+        }
+
+        Ok(Self::new(Keys::Set(keys), Algorithm::RS256))
+    }
+
+    pub fn rs256_from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let jwks = std::fs::read_to_string(path)?;
+        let jwks: JwkSet = serde_json::from_str(&jwks)?;
+
+        Ok(Self::rs256(&jwks)?)
+    }
+
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.validation.set_issuer(&[issuer]);
+        self
+    }
+
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.validation.set_audience(&[audience]);
+        self.validation.validate_aud = true;
+        self
+    }
+
+    fn new(keys: Keys, algorithm: Algorithm) -> Self {
+        let mut validation = Validation::new(algorithm);
+        // only checked when an audience is configured
+        validation.validate_aud = false;
+
+        Self { keys, validation }
+    }
+
+    fn decoding_key(&self, token: &str) -> Result<&DecodingKey, AuthError> {
+        match self.keys {
+            Keys::Shared(ref key) => Ok(key),
+            Keys::Set(ref keys) => {
+                let kid = decode_header(token)?.kid;
+                let key = match kid {
+                    Some(kid) => keys
+                        .iter()
+                        .find(|(id, _)| id.as_deref() == Some(kid.as_str())),
+                    None if keys.len() == 1 => keys.first(),
+                    None => None,
+                };
+
+                key.map(|(_, key)| key).ok_or(AuthError::UnknownKey)
+            }
+        }
+    }
+}
+
+impl Authenticator for JwtAuthenticator {
+    fn authenticate(&self, token: &str) -> Result<Identity, AuthError> {
+        let key = self.decoding_key(token)?;
+        let token = decode::<Claims>(token, key, &self.validation)?;
+
+        let user_id = Uuid::parse_str(&token.claims.sub).map_err(|_| AuthError::InvalidSubject)?;
+        let roles = token
+            .claims
+            .roles
+            .iter()
+            .filter_map(|role| role.parse::<Role>().ok());
+
+        Ok(Identity::new(user_id).with_roles(roles))
+    }
+}
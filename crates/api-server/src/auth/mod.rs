@@ -0,0 +1,45 @@
+mod jwt;
+
+use api_interface::Identity;
+use thiserror::Error;
+
+pub use jwt::JwtAuthenticator;
+
+/// Resolves the caller of a request from a bearer token
+pub trait Authenticator: Send + Sync {
+    fn authenticate(&self, token: &str) -> Result<Identity, AuthError>;
+}
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("authorization header is not a bearer token")]
+    MalformedHeader,
+    #[error("invalid token: {0}")]
+    InvalidToken(#[from] jsonwebtoken::errors::Error),
+    #[error("token subject is not a user id")]
+    InvalidSubject,
+    #[error("no key matches the token")]
+    UnknownKey,
+}
+
+/// Authenticates an `Authorization` header value, if one was sent. Requests without a
+/// header, or servers without an authenticator, are anonymous
+pub fn authenticate(
+    authenticator: Option<&dyn Authenticator>,
+    authorization: Option<&str>,
+) -> Result<Option<Identity>, AuthError> {
+    match (authenticator, authorization) {
+        (Some(authenticator), Some(authorization)) => {
+            let token = bearer_token(authorization).ok_or(AuthError::MalformedHeader)?;
+            authenticator.authenticate(token).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    let token = token.trim();
+
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
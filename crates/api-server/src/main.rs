@@ -1,6 +1,10 @@
+mod auth;
+mod outbox;
+mod purge;
 mod routes;
 mod state;
 mod telemetry;
+mod webhooks;
 
 #[cfg(test)]
 mod tests;
@@ -8,8 +12,8 @@ mod tests;
 use std::future::ready;
 
 use anyhow::Result;
+use api_interface::{ApiSchemaBuilder, Database};
 use async_graphql::extensions::Tracing;
-use async_graphql_axum::{GraphQL, GraphQLSubscription};
 use axum::{
     http::{header, HeaderValue, Method},
     middleware,
@@ -22,6 +26,7 @@ use tracing::info;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 use crate::routes::{
+    graphql::{graphql_handler, graphql_ws_handler, GraphQLState},
     handler,
     middleware::{graphql::Metrics, track_metrics},
 };
@@ -51,26 +56,63 @@ async fn main() -> Result<()> {
 }
 
 async fn create_router(state: state::AppState) -> Result<Router> {
-    let schema_builder = api_interface::ApiSchemaBuilder::new(
+    #[cfg(feature = "in-memory")]
+    if state.in_memory {
+        tracing::warn!("using in-memory storage, data will not be persisted");
+        let schema_builder = ApiSchemaBuilder::with_database(api_interface::InMemoryStore::new());
+        return build_router(schema_builder, state);
+    }
+
+    let schema_builder = ApiSchemaBuilder::new(
         state.database_credentials(),
         Some(state.redis_credentials()),
         Some(state.meilisearch_credentials()),
         state.apis(),
         state.bucket_details(),
     )
-    .await?
-    .with_extension(Tracing)
-    .with_extension(Metrics);
+    .await?;
 
-    let schema = schema_builder.build();
+    build_router(schema_builder, state)
+}
+
+fn build_router<D: Database>(
+    schema_builder: ApiSchemaBuilder<D>,
+    state: state::AppState,
+) -> Result<Router> {
+    purge::spawn(
+        schema_builder.database().clone(),
+        state.purge_retention,
+        state.purge_interval,
+    );
+    outbox::spawn(
+        schema_builder.database().clone(),
+        state.outbox_batch_size,
+        state.outbox_interval,
+    );
+    webhooks::spawn(
+        schema_builder.database().clone(),
+        state.webhook_batch_size,
+        state.webhook_interval,
+    );
+
+    let schema = schema_builder
+        .with_extension(Tracing)
+        .with_extension(Metrics)
+        .build();
+
+    let graphql_state = GraphQLState {
+        schema,
+        authenticator: state.authenticator.clone(),
+    };
 
     let router = Router::new()
-        .route("/", get(handler).post_service(GraphQL::new(schema.clone())))
+        .route("/", get(handler).post(graphql_handler::<D>))
+        .route(SUBSCRIPTION_ENDPOINT, get(graphql_ws_handler::<D>))
+        .with_state(graphql_state)
         .route(
             "/metrics",
             get(move || ready(state.metrics_handle.render())),
         )
-        .route_service(SUBSCRIPTION_ENDPOINT, GraphQLSubscription::new(schema))
         .route_layer(middleware::from_fn(track_metrics))
         // If you want to customize the behavior using closures here is how.
         .layer(TraceLayer::new_for_http().make_span_with(make_span))
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use api_interface::Database;
+use tokio::task::JoinHandle;
+use tracing::{error, instrument};
+
+/// Periodically delivers the listing changes recorded alongside every write, draining whatever
+/// is due before waiting for the next tick
+pub fn spawn<D: Database>(database: D, batch: usize, every: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            while dispatch(&database, batch).await == batch {}
+        }
+    })
+}
+
+#[instrument(skip(database), name = "listings.outbox")]
+async fn dispatch<D: Database>(database: &D, batch: usize) -> usize {
+    match database.dispatch_outbox(batch).await {
+        Ok(count) => count,
+        Err(e) => {
+            error!("{e}");
+            0
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+use api_interface::Database;
+use tokio::task::JoinHandle;
+use tracing::{error, info, instrument};
+
+/// Periodically hard-deletes listings that have been soft deleted for longer than `retention`
+pub fn spawn<D: Database>(database: D, retention: Duration, every: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            purge(&database, retention).await;
+        }
+    })
+}
+
+#[instrument(skip(database), name = "listings.purge")]
+async fn purge<D: Database>(database: &D, retention: Duration) {
+    match database.purge_deleted_listings(retention).await {
+        Ok(0) => {}
+        Ok(count) => info!(count, "purged deleted listings"),
+        Err(e) => error!("{e}"),
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use api_interface::{ApiSchema, Database};
+use async_graphql::{http::ALL_WEBSOCKET_PROTOCOLS, Data};
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
+use axum::{
+    extract::{State, WebSocketUpgrade},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use tracing::debug;
+
+use crate::auth::{self, Authenticator};
+
+pub struct GraphQLState<D: Database> {
+    pub schema: ApiSchema<D>,
+    pub authenticator: Option<Arc<dyn Authenticator>>,
+}
+
+impl<D: Database> Clone for GraphQLState<D> {
+    fn clone(&self) -> Self {
+        Self {
+            schema: self.schema.clone(),
+            authenticator: self.authenticator.clone(),
+        }
+    }
+}
+
+pub async fn graphql_handler<D: Database>(
+    State(state): State<GraphQLState<D>>,
+    headers: HeaderMap,
+    request: GraphQLRequest,
+) -> Result<GraphQLResponse, (StatusCode, String)> {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    let mut request = request.into_inner();
+    match auth::authenticate(state.authenticator.as_deref(), authorization) {
+        Ok(Some(identity)) => request = request.data(identity),
+        Ok(None) => {}
+        Err(e) => {
+            debug!("{e}");
+            return Err((StatusCode::UNAUTHORIZED, e.to_string()));
+        }
+    }
+
+    Ok(state.schema.execute(request).await.into())
+}
+
+pub async fn graphql_ws_handler<D: Database>(
+    State(state): State<GraphQLState<D>>,
+    protocol: GraphQLProtocol,
+    websocket: WebSocketUpgrade,
+) -> impl IntoResponse {
+    websocket
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            let authenticator = state.authenticator;
+            GraphQLWebSocket::new(stream, state.schema, protocol)
+                .on_connection_init(move |payload| on_connection_init(authenticator, payload))
+                .serve()
+        })
+}
+
+/// Authenticates a subscription from the `Authorization` entry of its `connection_init` payload
+async fn on_connection_init(
+    authenticator: Option<Arc<dyn Authenticator>>,
+    payload: serde_json::Value,
+) -> async_graphql::Result<Data> {
+    let authorization = payload
+        .get("Authorization")
+        .or_else(|| payload.get("authorization"))
+        .and_then(serde_json::Value::as_str);
+
+    let mut data = Data::default();
+    if let Some(identity) = auth::authenticate(authenticator.as_deref(), authorization)? {
+        data.insert(identity);
+    }
+
+    Ok(data)
+}
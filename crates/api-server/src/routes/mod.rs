@@ -1,3 +1,4 @@
+pub mod graphql;
 pub mod middleware;
 
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
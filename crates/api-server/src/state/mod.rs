@@ -1,11 +1,19 @@
 pub mod env;
 
+use std::{num::NonZeroUsize, sync::Arc, time::Duration};
+
 use anyhow::{Ok, Result};
 use api_interface::{Apis, DatabaseCredentials, RedisConfig, S3Config};
 use metrics_exporter_prometheus::PrometheusHandle;
 use tracing::{error, instrument, warn};
 
-use crate::telemetry::metrics::setup_metrics_recorder;
+use crate::{
+    auth::{Authenticator, JwtAuthenticator},
+    telemetry::metrics::setup_metrics_recorder,
+};
+
+/// Longest a deleted listing may be kept for before it is purged, about a century
+const MAX_RETENTION_DAYS: u64 = 36_500;
 
 pub struct AppState {
     pub port: u16,
@@ -20,11 +28,50 @@ pub struct AppState {
     redis_clustered: bool,
     db_pool_size: u16,
     cache_ttl: u64,
+    local_cache_size: Option<NonZeroUsize>,
+    local_cache_ttl: u64,
     meilisearch_host: String,
     meilisearch_api_key: Option<String>,
     api_users: String,
     api_categories: String,
     s3_config: S3Config,
+    pub purge_retention: Duration,
+    pub purge_interval: Duration,
+    pub outbox_batch_size: usize,
+    pub outbox_interval: Duration,
+    pub webhook_batch_size: usize,
+    pub webhook_interval: Duration,
+    pub authenticator: Option<Arc<dyn Authenticator>>,
+    #[cfg(feature = "in-memory")]
+    pub in_memory: bool,
+}
+
+fn authenticator_from_env() -> Result<Option<Arc<dyn Authenticator>>> {
+    let optional = |variable: &str| std::env::var(variable).ok().filter(|v| !v.is_empty());
+    let algorithm = env::extract_variable("JWT_ALGORITHM", "HS256");
+
+    let mut authenticator = match algorithm.as_str() {
+        "HS256" => match optional("JWT_SECRET") {
+            Some(secret) => JwtAuthenticator::hs256(secret.as_bytes()),
+            None => {
+                warn!("JWT_SECRET is not set, requests will not be authenticated");
+                return Ok(None);
+            }
+        },
+        "RS256" => {
+            JwtAuthenticator::rs256_from_file(env::extract_variable("JWT_JWKS_PATH", "jwks.json"))?
+        }
+        other => anyhow::bail!("unsupported JWT_ALGORITHM: {other}"),
+    };
+
+    if let Some(issuer) = optional("JWT_ISSUER") {
+        authenticator = authenticator.with_issuer(&issuer);
+    }
+    if let Some(audience) = optional("JWT_AUDIENCE") {
+        authenticator = authenticator.with_audience(&audience);
+    }
+
+    Ok(Some(Arc::new(authenticator)))
 }
 
 impl AppState {
@@ -71,6 +118,26 @@ impl AppState {
         let pool_size = env::extract_variable("DB_POOL_SIZE", "10");
         let cache_ttl = env::extract_variable("CACHE_TTL_MS", "5000");
 
+        let local_cache_size = env::extract_variable("LOCAL_CACHE_SIZE", "0");
+        let local_cache_size: usize = local_cache_size.parse().unwrap_or_else(|_| {
+            error!(
+                val = local_cache_size,
+                default = 0,
+                "local cache size invalid"
+            );
+            0
+        });
+
+        let local_cache_ttl = env::extract_variable("LOCAL_CACHE_TTL_MS", "1000");
+        let local_cache_ttl: u64 = local_cache_ttl.parse().unwrap_or_else(|_| {
+            error!(
+                val = local_cache_ttl,
+                default = 1000,
+                "local cache ttl invalid"
+            );
+            1000
+        });
+
         let meilisearch_host = env::extract_variable("MEILISEARCH_HOST", "http://localhost:7700");
         let meilisearch_api_key = env::extract_variable("MEILISEARCH_API_KEY", "");
         let meilisearch_api_key = if meilisearch_api_key.is_empty() {
@@ -89,6 +156,97 @@ impl AppState {
         let bucket_region = env::extract_variable("S3_BUCKET_REGION", "eu-central-1");
         let bucket_endpoint = env::extract_variable("S3_BUCKET_ENDPOINT", "http://localhost:19000");
 
+        let retention_days = env::extract_variable("LISTING_RETENTION_DAYS", "30");
+        // deleted listings are compared against now minus the retention, which must stay a date
+        let retention_days: u64 = retention_days
+            .parse()
+            .ok()
+            .filter(|days| *days <= MAX_RETENTION_DAYS)
+            .unwrap_or_else(|| {
+                error!(
+                    val = retention_days,
+                    default = 30,
+                    max = MAX_RETENTION_DAYS,
+                    "listing retention invalid"
+                );
+                30
+            });
+
+        let purge_interval = env::extract_variable("PURGE_INTERVAL_SECS", "3600");
+        let purge_interval: u64 = purge_interval
+            .parse()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .unwrap_or_else(|| {
+                error!(
+                    val = purge_interval,
+                    default = 3600,
+                    "purge interval invalid"
+                );
+                3600
+            });
+
+        let outbox_batch_size = env::extract_variable("OUTBOX_BATCH_SIZE", "100");
+        let outbox_batch_size: usize = outbox_batch_size
+            .parse()
+            .ok()
+            .filter(|size| *size > 0)
+            .unwrap_or_else(|| {
+                error!(
+                    val = outbox_batch_size,
+                    default = 100,
+                    "outbox batch size invalid"
+                );
+                100
+            });
+
+        let outbox_interval = env::extract_variable("OUTBOX_INTERVAL_SECS", "5");
+        let outbox_interval: u64 = outbox_interval
+            .parse()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .unwrap_or_else(|| {
+                error!(
+                    val = outbox_interval,
+                    default = 5,
+                    "outbox interval invalid"
+                );
+                5
+            });
+
+        let webhook_batch_size = env::extract_variable("WEBHOOK_BATCH_SIZE", "100");
+        let webhook_batch_size: usize = webhook_batch_size
+            .parse()
+            .ok()
+            .filter(|size| *size > 0)
+            .unwrap_or_else(|| {
+                error!(
+                    val = webhook_batch_size,
+                    default = 100,
+                    "webhook batch size invalid"
+                );
+                100
+            });
+
+        let webhook_interval = env::extract_variable("WEBHOOK_INTERVAL_SECS", "5");
+        let webhook_interval: u64 = webhook_interval
+            .parse()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .unwrap_or_else(|| {
+                error!(
+                    val = webhook_interval,
+                    default = 5,
+                    "webhook interval invalid"
+                );
+                5
+            });
+
+        let authenticator = authenticator_from_env()?;
+
+        #[cfg(feature = "in-memory")]
+        let in_memory = env::extract_variable("IN_MEMORY_DATABASE", "false");
+
         Ok(AppState {
             api_users,
             api_categories,
@@ -119,6 +277,8 @@ impl AppState {
                 error!(val = cache_ttl, default = 5000, "cache ttl invalid");
                 5000
             }),
+            local_cache_size: NonZeroUsize::new(local_cache_size),
+            local_cache_ttl,
             s3_config: S3Config {
                 bucket_name,
                 region: bucket_region,
@@ -126,6 +286,18 @@ impl AppState {
                 access_key: std::env::var("S3_ACCESS_KEY").ok(),
                 secret_key: std::env::var("S3_SECRET_KEY").ok(),
             },
+            purge_retention: Duration::from_secs(retention_days.saturating_mul(24 * 60 * 60)),
+            purge_interval: Duration::from_secs(purge_interval),
+            outbox_batch_size,
+            outbox_interval: Duration::from_secs(outbox_interval),
+            webhook_batch_size,
+            webhook_interval: Duration::from_secs(webhook_interval),
+            authenticator,
+            #[cfg(feature = "in-memory")]
+            in_memory: in_memory.parse().unwrap_or_else(|_| {
+                warn!("IN_MEMORY_DATABASE is not a boolean value, using the database");
+                false
+            }),
         })
     }
 
@@ -156,6 +328,8 @@ impl AppState {
             clustered: self.redis_clustered,
             pool_size: self.db_pool_size,
             ttl: self.cache_ttl,
+            local_cache_size: self.local_cache_size,
+            local_cache_ttl: self.local_cache_ttl,
         }
     }
 
@@ -1,3 +1,5 @@
+mod auth;
+
 use crate::{create_router, state::AppState};
 use anyhow::Result;
 use axum::{